@@ -1,59 +1,352 @@
-use crate::{Todo, TodoList};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use ratatui::layout::{Constraint, Layout, Rect};
+use crate::{DbFormat, ExportFormat, Finder, History, LoadError, LineNumbers, ListView, Paths, Picker, Popup, Todo, TodoList, TodoPrefix, TodoStyle, Theme, add_markdown_todos, byte_index, config_dir, contract_tilde, data_dir, elide_middle, expand_tilde, get_clipboard_text, git_commit_running, grapheme_count, list_profiles, next_word_start, poll_git_commit, prev_word_start, profile_dir, scroll_to_cursor, set_clipboard_text, start_git_commit, truncate_to_width, wait_for_git_commits, wrap_to_width, write_atomic};
+use unicode_segmentation::UnicodeSegmentation;
+use anyhow::{Context, anyhow, bail};
+use chrono::Local;
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use crossterm::event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags};
+use crossterm::terminal::supports_keyboard_enhancement;
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, Wrap};
 use ratatui::{DefaultTerminal, Frame};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::ops::Range;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use unicode_width::UnicodeWidthStr;
 
-const APP_VERSION: & str = "0.1";
+pub const APP_VERSION: & str = "0.2";
 const BACKLOG_LIST_IDX: usize = 1;
-const MOVE_HALF_AMOUNT: usize = 5;
+const MAX_SNAPSHOTS_LIMIT: usize = 10_000;
+const HINT_DELAY: Duration = Duration::from_millis(500);
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+const SIGNAL_CHECK_RATE: Duration = Duration::from_millis(250);
+const INSERT_SCRATCH_DELAY: Duration = Duration::from_secs(1); // Longest typing goes unwritten to the scratch file.
+const DB_CHECK_RATE: Duration = Duration::from_secs(2);         // How often the database is checked for changes on disk.
+
+/// Set by signals asking tdi to terminate, like SIGTERM and SIGHUP, checked while waiting for input.
+static TERMINATE: LazyLock<Arc<AtomicBool>> = LazyLock::new(|| Arc::new(AtomicBool::new(false)));
+const MIN_WIDTH: u16 = 20;                  // Narrowest terminal the board is drawn in.
+const MIN_LIST_HEIGHT: u16 = 3;             // Fewest rows a todo list is drawn in, its borders and a todo.
+
+/// New features and changed default key bindings of each version, oldest first.
+const CHANGELOG: &[(&str, &[&str])] = &[
+    ("0.2", &[
+        "ctrl+p opens a fuzzy finder for jumping to any todo",
+        "v enters visual mode, so d, m, H and L act on a range of todos",
+        "s sends the selected todo to a list chosen from a picker",
+        "i now places the cursor at the end of the todo instead of the start",
+        "I, a and A enter insert mode at the start, after the last edit, or at the end",
+        "S clears the selected todo and retypes it",
+        "Enter in insert mode starts editing a new todo below",
+        "Tab and Shift+Tab move the todo being edited between lists",
+        "ctrl+w, ctrl+u and ctrl+k delete text, ctrl+left and ctrl+right move by word",
+        "Text being typed is recovered if tdi exits mid-edit",
+        "p pastes the system clipboard as new todos, ctrl+v pastes it while editing",
+        "Y copies the selected todos to the system clipboard",
+        "# cycles line numbers between off, absolute and relative",
+        "? lists every key binding",
+        "Keys waiting for a register, like @ and ', show what each register holds after a moment",
+        "theme: light, dark or high-contrast in the config picks a color scheme",
+        "clock_format in the config shows a clock in the status bar",
+        "Enter shows the selected todo in full",
+        "the status bar hints at the keys for the current mode, hidden with key_hints: false",
+        "P toggles a preview of the selected todo's full name above the status bar",
+        "header: true in the config shows which database is being edited at the top",
+        "bullet and marked_bullet in the config change the glyphs todos are prefixed with",
+        "Clicking a todo selects it, unless mouse: false is set in the config",
+        "The mouse wheel moves the selection, or scrolls with wheel: scroll in the config",
+        "Double-clicking a todo edits it, with the cursor where it was clicked",
+        "ctrl+c saves and quits from any mode",
+        "tdi --db <path> and --config <path> use other files than the usual ones, as do TDI_DB and TDI_CONFIG",
+        "Saving keeps the last 3 versions of the database as db.yml.1 and so on, set with backup_count",
+        "autosave: immediate or autosave: {debounce: 5} in the config saves without quitting",
+        "ctrl+s saves right away",
+        "ctrl+q quits without saving, after asking if there are changes",
+        "A database that can't be read is moved aside instead of stopping tdi from starting",
+        "db_format: json in the config, or a dbpath ending in .json, saves the database as JSON",
+        "The config can be written in TOML as config.toml, which is used over config.yml",
+        "db_format: sqlite, or a dbpath ending in .sqlite, keeps the database in SQLite",
+        "tdi --import <path> copies a database into a new one, like from YAML into SQLite",
+        "ctrl+r reloads the database from disk, after edits made outside tdi",
+        "The database is reloaded when changed on disk, like by a sync tool, unless auto_reload: false is set",
+        "E exports the board as Markdown or CSV, to a path ending in .md or .csv, as does tdi --export <path>",
+        "A count before @ replays a macro that many times, like 3@a",
+        "ctrl+e adds the todos of a Markdown file to the board, as does tdi --import <path> for a path ending in .md",
+        "git_autocommit: true in the config commits the database to git on every save, if it's in a git repository",
+        "tdi --readonly, or read_only: true in the config, views the board without ever changing it",
+        "tdi --profile <name> uses a config and database of its own, in directories named after it",
+        "tdi reopens where it was left, with the same todo selected, kept apart from the database in db.yml.ui-state",
+        "max_snapshots in the config sets how many changes undo can go back, 100 by default",
+    ]),
+];
 
 
 #[derive(Clone, Eq, PartialEq)]
 pub struct App {
     config: Config,
     todo_lists: Vec<TodoList>,                      // All todo lists.
+    marks: BTreeMap<char, u64>,                     // Ids of todos bookmarked by letter.
+    next_todo_id: u64,                              // Id given to the next todo created.
     selection: Selection,                           // What is currently selected by the user.
     mode: Mode,                                     // Mode of the app, influencing key presses.
     key_mappings: HashMap<KeyPress, Action>,        // Maps key presses to actions while in a given mode.
-    snapshots: VecDeque<State>,                     // Snapshots of the app's state, used for undo/redo functionality.
+    theme: Theme,                                   // Colors to draw with, from the config.
+    todo_prefix: TodoPrefix,                        // What todos are prefixed with, from the config.
+    finder: Finder,                                 // Fuzzy finder overlay, populated when entering finder mode.
+    picker: Picker,                                 // Todo list picker, populated when sending a todo to another list.
+    popup: Popup,                                   // Read-only text popup, shown in popup mode.
+    confirm: Option<Confirm>,                       // Pending yes/no question, answered in confirm mode.
+    prompt: Option<Prompt>,                         // Line of text being typed in prompt mode, like a path to export to.
+    last_insert: Option<Selection>,                 // Selection when insert mode was last exited, used to resume editing.
+    insert_original: String,                        // Name of the todo being edited, before the insert session began.
+    insert_scratch_due: Option<Instant>,            // Time the insert buffer is next written to its scratch file, if typed in since.
+    history: History<State>,                        // Snapshots of the app's state, used for undo/redo functionality.
     needs_saving: bool,                             // Set to true if a change occurred, requiring saving.
-    current_snapshot: usize, 
-    max_snapshots: usize, 
+    save_failed: bool,                              // Set to true if the last save failed, until one succeeds.
+    pending_register: Option<RegisterAction>,       // Action waiting for a register to be typed.
+    recording: Option<(char, Vec<Action>)>,         // Register and actions of the macro being recorded.
+    macros: HashMap<char, Vec<Action>>,             // Recorded macros by register.
+    last_replayed: Option<char>,                    // Register of the macro last replayed.
+    replaying: bool,                                // True while replaying a macro.
+    count: Option<usize>,                           // Count typed before replaying a macro, like the 3 of 3@a.
+    sort_order: Option<(usize, SortOrder)>,         // List last sorted and the order it was sorted by, if sorting was the last action.
+    list_height: u16,                               // Number of todos that fit in a todo list, as last rendered.
+    scroll_offsets: Vec<usize>,                     // Index of the first row shown in each todo list.
+    list_areas: Vec<Rect>,                          // Area of each todo list, as last rendered.
+    message: Option<Message>,                       // Message shown in the bottom row until the next key press, or it expires.
+    show_hints: bool,                               // True once the pending register has been waited on long enough to show hints.
+    last_click: Option<(Instant, u16, u16)>,        // Time, column and row of the last click, to detect double clicks.
+    autosave_from: Instant,                         // Time autosave waits from: the last key press, or failed autosave.
+    db_stamp: Option<(SystemTime, u64)>,            // Modification time and size of the database as last loaded or saved.
+    db_checked_at: Instant,                         // When the database on disk was last checked for changes.
+    saved_hash: Option<u64>,                        // Hash of the board in the database, if it's in the format saved in.
     quit: bool,
 }
 
 impl App {
-/// Creates and initializes the application.
-pub fn init() -> anyhow::Result<Self> {
-        let config = load_app_config()?;
+/// Creates and initializes the application, with paths overridden by the options given or the environment.
+pub fn init(options: Options) -> anyhow::Result<Self> {
+        let config = load_config(&options)?;
         let dbpath = &config.dbpath;
-        let state = match Path::new(dbpath).exists() {
-            true => load_app_state(dbpath)?,
-            false => State::default(),
+        let first_run = !Path::new(dbpath).exists();
+        if first_run && config.read_only {
+            bail!("Database {dbpath} does not exist, so there is nothing to view");
+        }
+        let (state, loaded_format, corrupt_db) = match (first_run, config.sample_todos) {
+            // Left as it is even if corrupt, rather than moved aside.
+            (false, _) if config.read_only => (load_db(dbpath).with_context(|| format!("Could not open {dbpath}"))?, None, None),
+            (false, _) => load_app_state(dbpath).map(|(state, format, corrupt_db)| (state, Some(format), corrupt_db))?,
+            (true, true) => (State::welcome(), None, None),
+            (true, false) => (State::default(), None, None),
         };
-        Ok(Self {
+        let db_hash = state_hash(&state);
+        let (theme, theme_warnings) = Theme::from_config(&config.theme);
+        let todo_prefix = TodoPrefix::new(config.todo_style, config.bullet.as_deref(), config.marked_bullet.as_deref());
+        let max_snapshots = config.max_snapshots;
+        let mut app = Self {
             config,
             todo_lists: state.todo_lists,
+            marks: state.marks,
+            next_todo_id: 1,
             selection: Selection::default(),
             mode: Mode::Normal,
             key_mappings: default_key_mappings(),
-            snapshots: VecDeque::new(),
+            theme,
+            todo_prefix,
+            finder: Finder::default(),
+            picker: Picker::default(),
+            popup: Popup::default(),
+            confirm: None,
+            prompt: None,
+            last_insert: None,
+            insert_original: String::new(),
+            insert_scratch_due: None,
+            history: History::new(max_snapshots),
             needs_saving: false,
-            current_snapshot: 0,
-            max_snapshots: 100,
+            save_failed: false,
+            pending_register: None,
+            recording: None,
+            macros: HashMap::new(),
+            last_replayed: None,
+            replaying: false,
+            count: None,
+            sort_order: None,
+            list_height: 0,
+            scroll_offsets: vec![],
+            list_areas: vec![],
+            message: None,
+            show_hints: false,
+            last_click: None,
+            autosave_from: Instant::now(),
+            db_stamp: None,
+            db_checked_at: Instant::now(),
+            saved_hash: None,
             quit: false,
-        })
+        };
+        app.db_stamp = app.read_db_stamp();
+        app.assign_todo_ids();
+        app.load_history(db_hash);
+        app.load_ui_state();
+        if loaded_format.is_some_and(|format| format != app.db_format()) {
+            app.needs_saving = true; // Converts the database to the format configured.
+        }
+        else if loaded_format.is_some() {
+            app.saved_hash = Some(db_hash);
+        }
+        if first_run {
+            app.post_message(MessageLevel::Info, "Welcome to tdi! Press ? to see every key binding");
+            app.needs_saving = true; // Keeps the welcome board, so it is only shown once.
+        }
+        if !theme_warnings.is_empty() {
+            app.post_message(MessageLevel::Warning, format!("Theme: {}", theme_warnings.join(", ")));
+        }
+        let scratch = match app.config.read_only {
+            true => None, // Left for the next session that can save it.
+            false => app.load_insert_scratch(),
+        };
+        if let Some(corrupt_db) = corrupt_db {
+            app.popup = Popup::new("Database could not be read", corrupt_db.explanation());
+            app.mode = Mode::Popup;
+            app.post_message(MessageLevel::Error, format!("Database was moved to {}", contract_tilde(&corrupt_db.moved_to)));
+            app.needs_saving = true; // Starts a new database in its place.
+        }
+        else if let Some(lines) = whats_new(&state.version) {
+            app.popup = Popup::new(format!("What's new in tdi {APP_VERSION}"), lines);
+            app.mode = Mode::Popup;
+            app.needs_saving = true; // Records the version seen, so this is only shown once.
+        }
+        // Asks about restoring an interrupted edit first. Any popup is shown once that's answered.
+        if let Some(scratch) = scratch {
+            app.confirm = Some(Confirm::RestoreInsert(scratch));
+            app.mode = Mode::Confirm;
+        }
+        if app.config.read_only {
+            app.needs_saving = false;
+        }
+        Ok(app)
+    }
+
+    /// Copies the database at `import_path` into a new database, where the options and config say and in the format
+    /// configured, like to move from YAML to SQLite. A Markdown file's todos are added to the database instead.
+    /// Returns what was done, to print.
+    pub fn import(options: &Options, import_path: &str) -> anyhow::Result<String> {
+        let config = load_config(options)?;
+        if config.read_only {
+            bail!("Can't import into {}, since it is read-only", config.dbpath);
+        }
+        let import_path = expand_tilde(import_path);
+        if matches!(ExportFormat::for_path(&import_path), Ok(ExportFormat::Markdown)) {
+            return import_markdown(&config, &import_path);
+        }
+        let dbpath = &config.dbpath;
+        if Path::new(dbpath).exists() {
+            bail!("Database {dbpath} already exists. Move it away first, or import into another with --db");
+        }
+        let mut state = load_db(&import_path).with_context(|| format!("Could not import {import_path}"))?;
+        state.version = APP_VERSION.to_string();
+        if let Some(parent) = Path::new(dbpath).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let db_format = config.db_format.unwrap_or_else(|| DbFormat::for_path(dbpath));
+        db_format.storage(dbpath).save(&state)?;
+        let todo_count: usize = state.todo_lists.iter().map(|todo_list| todo_list.todos.len()).sum();
+        let todo_list_count = state.todo_lists.len();
+        Ok(format!("Imported {todo_count} todos in {todo_list_count} lists from {import_path} into {dbpath}"))
+    }
+
+    /// Writes the board of the database the options and config say to `export_path`, in the format its extension
+    /// names, like Markdown for .md. An existing file is only overwritten if `confirm_overwrite` agrees.
+    /// Returns what was done, to print.
+    pub fn export(options: &Options, export_path: &str, confirm_overwrite: impl FnOnce(&str) -> bool) -> anyhow::Result<String> {
+        let config = load_config(options)?;
+        let export_path = expand_tilde(export_path);
+        let format = ExportFormat::for_path(&export_path)?;
+        let dbpath = &config.dbpath;
+        let state = load_db(dbpath).with_context(|| format!("Could not export {dbpath}"))?;
+        if Path::new(&export_path).exists() && !confirm_overwrite(&export_path) {
+            return Ok(format!("Left {export_path} as it was"));
+        }
+        write_atomic(Path::new(&export_path), format.export(&state.todo_lists).as_bytes())?;
+        Ok(format!("Exported {dbpath} to {export_path}"))
+    }
+
+    /// Names of the profiles there are, to pick from with --profile.
+    pub fn list_profiles() -> anyhow::Result<Vec<String>> {
+        list_profiles()
     }
 
     /// Consumes and runs application.
     pub fn run(mut self, mut terminal: DefaultTerminal) -> anyhow::Result<()> {
+        watch_terminate_signals()?;
+        // Terminals supporting the kitty keyboard protocol report keys unambiguously, like ctrl+i apart from tab.
+        let keyboard_enhanced = matches!(supports_keyboard_enhancement(), Ok(true));
+        if keyboard_enhanced {
+            let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES;
+            crossterm::execute!(std::io::stdout(), PushKeyboardEnhancementFlags(flags))?;
+        }
+        let mouse = self.config.mouse;
+        if mouse {
+            crossterm::execute!(std::io::stdout(), EnableMouseCapture)?;
+        }
+
+        // The panic hook installed by ratatui restores the terminal, but not the modes turned on here.
+        let ratatui_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = leave_terminal_modes(mouse, keyboard_enhanced);
+            ratatui_hook(info);
+        }));
+        let result = match std::panic::catch_unwind(AssertUnwindSafe(|| self.run_loop(&mut terminal))) {
+            Ok(result) => result,
+            Err(_) => Err(self.recover_from_crash()),
+        };
+        if result.is_ok() {
+            self.save_ui_state();
+        }
+        let committed = wait_for_git_commits().context("Could not commit the database to git");
+        leave_terminal_modes(mouse, keyboard_enhanced)?;
+        result.and(committed)
+    }
+
+    /// Writes unsaved changes to a recovery file next to the database after a panic, as best it can,
+    /// since the state may be left inconsistent. Returns the error to report, saying where they went.
+    fn recover_from_crash(&self) -> anyhow::Error {
+        if !self.needs_saving {
+            return anyhow!("tdi crashed. There were no unsaved changes");
+        }
+        let crash_path = format!("{}.crash", self.config.dbpath);
+        let write_crash_file = || -> anyhow::Result<()> {
+            if let Some(parent) = Path::new(&crash_path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let crash_format = match self.db_format() {
+                DbFormat::Json => DbFormat::Json,
+                DbFormat::Yaml | DbFormat::Sqlite => DbFormat::Yaml, // Readable, to pick out the changes by hand.
+            };
+            crash_format.storage(&crash_path).save(&State::create(self))?;
+            Ok(())
+        };
+        let written = write_crash_file();
+        match written {
+            Ok(()) => anyhow!("tdi crashed. Unsaved changes were written to {crash_path}"),
+            Err(err) => anyhow!("tdi crashed, and unsaved changes could not be written to {crash_path}: {err}"),
+        }
+    }
+
+    fn run_loop(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
         loop {
             terminal.draw(|frame| self.render(frame))?;
             let action = self.read_next_action()?;
-            self.update(action)?;
+            if let Err(err) = self.update(action) {
+                self.post_message(MessageLevel::Error, format!("{err:#}"));
+            }
             if self.quit {
                 break;
             }
@@ -62,17 +355,42 @@ pub fn init() -> anyhow::Result<Self> {
     }
 
     /// Waits for an event, input, then returns the corresponding action
+    /// Gives up waiting when something is due to change by itself, like hints appearing or a message expiring.
+    /// Also wakes up regularly to check for signals asking tdi to terminate.
     fn read_next_action(&self) -> anyhow::Result<Action> {
+        let deadline = self.next_timeout().map(|(timeout, action)| (Instant::now() + timeout, action));
         loop {
+            if TERMINATE.load(Ordering::Relaxed) {
+                return Ok(Action::Terminate);
+            }
+            let until_deadline = deadline.map_or(SIGNAL_CHECK_RATE, |(at, _)| at.saturating_duration_since(Instant::now()));
+            if !event::poll(until_deadline.min(SIGNAL_CHECK_RATE))? {
+                match deadline {
+                    Some((at, action)) if Instant::now() >= at => return Ok(action),
+                    _ => continue,
+                }
+            }
             match event::read()? {
                 Event::Key(KeyEvent { code, kind: KeyEventKind::Press, modifiers, .. }) => {
-                    let key_press = KeyPress { mode: self.mode, code, modifiers };
-                    if let Some(action) = self.key_mappings.get(&key_press) {
-                        return Ok(*action);
-                    } else if self.mode == Mode::Insert {
-                        return Ok(Action::Input(code));
+                    if let Some(action) = self.key_action(code, modifiers) {
+                        return Ok(action);
                     }
                 }
+                Event::Mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column, row, .. })
+                    if self.pending_register.is_none() => {
+                    return Ok(Action::Click { column, row });
+                }
+                Event::Mouse(MouseEvent { kind, column, row, modifiers }) if self.pending_register.is_none() => {
+                    let sideways = modifiers.contains(KeyModifiers::SHIFT);
+                    let direction = match (kind, sideways) {
+                        (MouseEventKind::ScrollUp, false) => WheelDirection::Up,
+                        (MouseEventKind::ScrollDown, false) => WheelDirection::Down,
+                        (MouseEventKind::ScrollLeft, _) | (MouseEventKind::ScrollUp, true) => WheelDirection::Left,
+                        (MouseEventKind::ScrollRight, _) | (MouseEventKind::ScrollDown, true) => WheelDirection::Right,
+                        _ => continue,
+                    };
+                    return Ok(Action::Wheel { column, row, direction });
+                }
                 Event::Resize(_, _) => {
                     return Ok(Action::Nop);
                 }
@@ -81,53 +399,343 @@ pub fn init() -> anyhow::Result<Self> {
         }
     }
 
+    /// Action a key press maps to in the current mode, if any.
+    /// Keys without a mapping are typed as text in modes that take text, and ignored elsewhere.
+    fn key_action(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        if let Some(register_action) = self.pending_register {
+            return match code {
+                KeyCode::Char(register) => Some(Action::WithRegister(register_action, register)),
+                _ => Some(Action::CancelRegister),
+            };
+        }
+        let key_press = KeyPress::new(self.mode, code, modifiers);
+        if let Some(action) = self.key_mappings.get(&key_press) {
+            Some(*action)
+        } else if matches!(self.mode, Mode::Insert | Mode::Finder | Mode::Prompt) {
+            Some(Action::Input(key_press.code))
+        } else {
+            None
+        }
+    }
+
+    /// Soonest change due without a key press, as the time until it and the action making it.
+    fn next_timeout(&self) -> Option<(Duration, Action)> {
+        let mut timeouts = vec![];
+        if self.pending_register.is_some() && self.config.which_key && !self.show_hints {
+            timeouts.push((HINT_DELAY, Action::ShowHints));
+        }
+        if let Some(message) = &self.message {
+            timeouts.push((MESSAGE_DURATION.saturating_sub(message.posted_at.elapsed()), Action::ExpireMessage));
+        }
+        if let (Autosave::Debounce(secs), true) = (self.config.autosave, self.can_autosave()) {
+            let delay = Duration::from_secs(secs).saturating_sub(self.autosave_from.elapsed());
+            timeouts.push((delay, Action::Autosave));
+        }
+        if let Some(due) = self.insert_scratch_due {
+            timeouts.push((due.saturating_duration_since(Instant::now()), Action::Tick));
+        }
+        if self.needs_tick() {
+            timeouts.push((Duration::from_millis(self.config.tick_rate_ms.max(1)), Action::Tick));
+        }
+        if self.config.auto_reload && self.mode == Mode::Normal {
+            timeouts.push((DB_CHECK_RATE.saturating_sub(self.db_checked_at.elapsed()), Action::Tick));
+        }
+        timeouts.into_iter().min_by_key(|(timeout, _)| *timeout)
+    }
+
+    /// True if anything changes with time alone, like the clock, so ticks should be sent every `tick_rate_ms`
+    /// while idle.
+    /// Otherwise tdi sleeps until input arrives, or it is time to check the database on disk for changes.
+    fn needs_tick(&self) -> bool {
+        self.config.clock_format.is_some() || git_commit_running()
+    }
+
+    /// Things to do on every tick. The screen is redrawn after each, so it also keeps the clock up to date.
+    fn tick(&mut self) {
+        if self.insert_scratch_due.is_some_and(|due| Instant::now() >= due) {
+            self.write_insert_scratch();
+        }
+        self.check_db_changed();
+        if let Some(Err(err)) = poll_git_commit() {
+            self.post_message(MessageLevel::Error, format!("Could not commit the database to git: {err:#}"));
+        }
+    }
+
+    /// Modification time and size of the database on disk, if it exists.
+    fn read_db_stamp(&self) -> Option<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(&self.config.dbpath).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    /// Reloads the database if something else changed it since it was last loaded or saved, like a sync tool.
+    /// If there are unsaved changes, asks whether to reload and lose them, or keep them instead.
+    /// Only checked in normal mode, so nothing changes mid-edit. Otherwise the change is noticed once back.
+    fn check_db_changed(&mut self) {
+        if !self.config.auto_reload || self.mode != Mode::Normal {
+            return;
+        }
+        self.db_checked_at = Instant::now();
+        let db_stamp = self.read_db_stamp();
+        if db_stamp.is_none() || db_stamp == self.db_stamp {
+            return;
+        }
+        self.db_stamp = db_stamp; // Asks once per change, and leaves databases that failed to reload until fixed.
+        if self.needs_saving {
+            self.confirm = Some(Confirm::ChangedOnDisk);
+            self.mode = Mode::Confirm;
+            return;
+        }
+        match self.reload() {
+            Ok(()) => self.post_message(MessageLevel::Info, "Reloaded, since the database changed on disk"),
+            Err(err) => self.post_message(MessageLevel::Error, format!("{err:#}")),
+        }
+    }
+
+    /// Shows a message in the bottom row until the next key press, or it expires.
+    fn post_message(&mut self, level: MessageLevel, text: impl Into<String>) {
+        self.message = Some(Message {
+            level,
+            text: text.into(),
+            posted_at: Instant::now(),
+        });
+    }
+
     /// Waits for user input, then updates state.
     /// Returns true if application should quit.
     fn update(&mut self, action: Action) -> anyhow::Result<()> {
+        if action.is_key_press() {
+            if action != Action::SortList {
+                self.sort_order = None;
+            }
+            if !action.takes_count() {
+                self.count = None;
+            }
+            self.message = None;
+        }
+        if let Some((_, actions)) = &mut self.recording {
+            if action.is_recordable() && !self.replaying {
+                actions.push(action);
+            }
+        }
+        if self.config.read_only && action.is_mutating() {
+            self.post_message(MessageLevel::Warning, "Read-only: the board can't be changed");
+            return Ok(());
+        }
         match action {
             Action::Quit => self.quit()?,
-            Action::DeleteTodo => self.delete_todo(),
+            Action::Save => self.save_now()?,
+            Action::ForceQuit => self.confirm_force_quit(),
+            Action::Reload => self.confirm_reload()?,
+            Action::Export => self.open_prompt(PromptKind::Export),
+            Action::Import => self.open_prompt(PromptKind::Import),
+            Action::Terminate => self.terminate()?,
+            Action::DeleteTodo => self.confirm_delete_todo(),
+            Action::DeleteMarked => self.confirm_delete_marked(),
+            Action::SortList => self.sort_list(),
+            Action::ShowHelp => self.show_help(),
+            Action::ShowDetail => self.show_detail(),
+            Action::PopupScrollDown => self.popup.scroll_down(),
+            Action::PopupScrollUp => self.popup.scroll_up(),
+            Action::ToggleLineNumbers => self.config.line_numbers = self.config.line_numbers.next(),
+            Action::TogglePreview => self.config.preview = !self.config.preview,
             Action::MoveTodoLeft => self.move_todo_left(),
             Action::MoveTodoRight => self.move_todo_right(),
             Action::MoveTodoUp => self.move_todo_up(),
             Action::MoveTodoDown => self.move_todo_down(),
+            Action::MoveTodoTop => self.move_todo_top(),
+            Action::MoveTodoBottom => self.move_todo_bottom(),
             Action::SetMode(mode) => self.set_mode(mode),
+            Action::InsertStart => self.insert_start(),
+            Action::Append => self.append(),
+            Action::ChangeTodo => self.change_todo(),
+            Action::InsertNext => self.insert_next(),
             Action::MoveLeft => self.move_left(),
             Action::MoveRight => self.move_right(),
             Action::MoveUp => self.move_up(),
             Action::MoveDown => self.move_down(),
             Action::MoveUpHalf => self.move_up_half(),
             Action::MoveDownHalf => self.move_down_half(),
+            Action::MovePageUp => self.move_page_up(),
+            Action::MovePageDown => self.move_page_down(),
             Action::MoveTop => self.move_top(),
             Action::MoveBottom => self.move_bottom(),
             Action::AddTodoAbove => self.add_todo(false),
             Action::AddTodoBelow => self.add_todo(true),
             Action::ToggleMark => self.toggle_mark(),
             Action::Input(code) => self.input(code),
+            Action::PasteClipboard => self.paste_clipboard(),
+            Action::YankToClipboard => self.yank_to_clipboard(),
             Action::MoveCursorRight => self.move_cursor_right(),
             Action::MoveCursorLeft => self.move_cursor_left(),
             Action::MoveCursorStart => self.move_cursor_start(),
             Action::MoveCursorEnd => self.move_cursor_end(),
+            Action::MoveCursorWordForward => self.move_cursor_word_forward(),
+            Action::MoveCursorWordBackward => self.move_cursor_word_backward(),
+            Action::DeleteWordBackward => self.delete_word_backward(),
+            Action::DeleteToStart => self.delete_to_start(),
+            Action::DeleteToEnd => self.delete_to_end(),
+            Action::MoveEditingTodoLeft => self.move_editing_todo(false),
+            Action::MoveEditingTodoRight => self.move_editing_todo(true),
+            Action::ConfirmYes => self.answer_confirm(true)?,
+            Action::ConfirmNo => self.answer_confirm(false)?,
+            Action::SubmitPrompt => self.submit_prompt()?,
+            Action::FinderNext => self.finder.select_next(),
+            Action::FinderPrev => self.finder.select_prev(),
+            Action::FinderJump => self.finder_jump(),
+            Action::SendTodoTo => self.open_send_picker(),
+            Action::PickerNext => self.picker.select_next(),
+            Action::PickerPrev => self.picker.select_prev(),
+            Action::PickerConfirm => self.send_todo(self.picker.selected),
+            Action::PickerChoose(item_idx) => self.send_todo(item_idx),
             Action::Undo => self.undo(),
             Action::Redo => self.redo(),
+            Action::RecordMacro => self.record_macro(),
+            Action::AwaitRegister(register_action) => {
+                self.pending_register = Some(register_action);
+                self.show_hints = false;
+            }
+            Action::Click { column, row } => self.click(column, row),
+            Action::Wheel { column, row, direction } => self.wheel(column, row, direction),
+            Action::ShowHints => self.show_hints = true,
+            Action::ExpireMessage => self.message = None,
+            Action::Tick => self.tick(),
+            Action::CancelRegister => self.pending_register = None,
+            Action::Count(digit) => self.push_count_digit(digit),
+            Action::WithRegister(register_action, register) => {
+                self.pending_register = None;
+                match register_action {
+                    RegisterAction::RecordMacro => self.recording = Some((register, vec![])),
+                    RegisterAction::ReplayMacro => {
+                        for _ in 0..self.count.take().unwrap_or(1) {
+                            self.replay_macro(register)?;
+                        }
+                    }
+                    RegisterAction::SetMark => self.set_mark(register),
+                    RegisterAction::JumpToMark => self.jump_to_mark(register),
+                }
+            }
             Action::Nop => {}
+            Action::Autosave => {
+                self.autosave_from = Instant::now(); // Retries after the same delay if saving fails.
+                self.save()?;
+            }
+        }
+        if action.is_key_press() {
+            self.autosave_from = Instant::now();
+            if self.config.autosave == Autosave::Immediate && self.can_autosave() && !self.replaying {
+                self.save()?;
+            }
         }
         Ok(())
     }
 
+    /// True if there are unsaved changes autosave could write now. Nothing is saved while a todo is being edited,
+    /// so typing is never interrupted and a session is saved once finished. Nor is anything saved while asking
+    /// whether to quit without saving, or after choosing to.
+    fn can_autosave(&self) -> bool {
+        self.needs_saving && !matches!(self.mode, Mode::Insert | Mode::Confirm) && !self.quit
+    }
+
+    /// Stops recording a macro, or asks for the register to record one into.
+    fn record_macro(&mut self) {
+        match self.recording.take() {
+            Some((register, actions)) => { self.macros.insert(register, actions); }
+            None => {
+                self.pending_register = Some(RegisterAction::RecordMacro);
+                self.show_hints = false;
+            }
+        }
+    }
+
+    /// Registers worth typing after a register action, and what each holds.
+    fn register_hints(&self, register_action: RegisterAction) -> Vec<String> {
+        let mut hints = vec![];
+        match register_action {
+            RegisterAction::SetMark | RegisterAction::JumpToMark => {
+                for (mark, id) in &self.marks {
+                    if let Some((todo_list_idx, todo_idx)) = self.find_todo(*id) {
+                        hints.push(format!("{mark} → {}", self.todo_lists[todo_list_idx].todos[todo_idx].name));
+                    }
+                }
+            }
+            RegisterAction::RecordMacro | RegisterAction::ReplayMacro => {
+                if let (RegisterAction::ReplayMacro, Some(register)) = (register_action, self.last_replayed) {
+                    hints.push(format!("@ → last macro, {register}"));
+                }
+                let mut registers: Vec<&char> = self.macros.keys().collect();
+                registers.sort();
+                for register in registers {
+                    hints.push(format!("{register} → macro of {} actions", self.macros[register].len()));
+                }
+            }
+        }
+        if register_action == RegisterAction::SetMark || register_action == RegisterAction::RecordMacro {
+            hints.push("any other key → new register".to_string());
+        }
+        hints.push("esc → cancel".to_string());
+        hints
+    }
+
+    /// Adds a digit to the count for the next macro replay. A leading 0 is ignored, as there is no use replaying nothing.
+    fn push_count_digit(&mut self, digit: usize) {
+        if self.count.is_none() && digit == 0 {
+            return;
+        }
+        self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+    }
+
+    /// Replays the macro recorded into a register through [`App::update`], so it behaves just like typing it.
+    /// `@` replays the last macro replayed. Macros replaying other macros are not followed, to avoid recursion.
+    /// Replaying while recording records the keys replaying, not what they replay.
+    fn replay_macro(&mut self, register: char) -> anyhow::Result<()> {
+        if self.replaying {
+            return Ok(());
+        }
+        let register = match register {
+            '@' => match self.last_replayed {
+                Some(register) => register,
+                None => return Ok(()),
+            },
+            register => register,
+        };
+        let Some(actions) = self.macros.get(&register).cloned() else { return Ok(()) };
+        self.last_replayed = Some(register);
+        self.replaying = true;
+        let result = actions.into_iter().try_for_each(|action| self.update(action));
+        self.replaying = false;
+        result
+    }
+
     /// Draws user interface.
-    fn render(&self, frame: &mut Frame) {
+    fn render(&mut self, frame: &mut Frame) {
         // Computes areas to render in
         let area = frame.area();
+        let preview_lines = match self.config.preview {
+            true => self.preview_lines(area.width as usize),
+            false => vec![],
+        };
+        let preview_height = preview_lines.len() as u16;
+        let header_height = self.config.header as u16;
+        let min_height = header_height + MIN_LIST_HEIGHT + preview_height + 1;
+        if area.width < MIN_WIDTH || area.height < min_height {
+            let placeholder = format!("terminal too small (need at least {MIN_WIDTH}x{min_height})");
+            let paragraph = Paragraph::new(placeholder)
+                .wrap(Wrap { trim: true })
+                .centered()
+                .fg(self.theme.fg_warning);
+            frame.render_widget(paragraph, area);
+            self.list_areas.clear();
+            return;
+        }
         let content_area = Rect {
             x: area.x,
-            y: area.y,
+            y: area.y + header_height,
             width: area.width,
-            height: area.height - 1,
+            height: area.height - 1 - preview_height - header_height,
         };
         let bottom_area = Rect {
             x: area.x,
-            y: area.height - 1,
+            y: area.bottom() - 1,
             width: area.width,
             height: 1,
         };
@@ -136,35 +744,203 @@ pub fn init() -> anyhow::Result<Self> {
             .direction(ratatui::layout::Direction::Horizontal)
             .constraints(constraints)
             .split(content_area);
+        self.list_height = content_area.height.saturating_sub(2);
+
+        if self.config.header {
+            let header_area = Rect { height: 1, ..area };
+            frame.render_widget(Line::from(self.header(area.width as usize)).fg(self.theme.border_selected).centered(), header_area);
+        }
 
         // Renders todo lists
-        if !self.todo_lists.is_empty() {
-            let todo_list_idx = self.selection.todo_list;
-            let todo_list_idx = todo_list_idx.min(self.todo_lists.len() - 1);
-            for (i, (todo_list, todo_list_area)) in self
-                .todo_lists
-                .iter()
-                .zip(list_areas.iter().copied())
-                .enumerate()
-            {
-                let is_list_selected = i == todo_list_idx;
-                todo_list.render(
-                    is_list_selected,
-                    self.selection.todo,
-                    self.selection.char,
-                    self.mode,
-                    todo_list_area,
-                    frame,
-                );
-            }
-        }
-
-        // Renders bottom row
-        let mode_text = match self.mode {
-            Mode::Normal => "Normal",
-            Mode::Insert => "Insert",
+        self.scroll_offsets.resize(self.todo_lists.len(), 0);
+        for (i, (todo_list, todo_list_area)) in self
+            .todo_lists
+            .iter()
+            .zip(list_areas.iter().copied())
+            .enumerate()
+        {
+            self.scroll_offsets[i] = todo_list.scroll_into_view(&self.list_view(i), todo_list_area);
+            todo_list.render(&self.list_view(i), todo_list_area, frame);
+        }
+        self.list_areas = list_areas.to_vec();
+
+        // Renders the preview of the selected todo
+        for (i, line) in preview_lines.into_iter().enumerate() {
+            let line_area = Rect {
+                y: content_area.bottom() + i as u16,
+                height: 1,
+                ..content_area
+            };
+            frame.render_widget(Line::from(line).fg(self.theme.fg_unselected), line_area);
+        }
+
+        self.render_status_bar(bottom_area, frame);
+
+        // Renders overlays
+        if let (Some(register_action), true) = (self.pending_register, self.show_hints) {
+            let title = Action::AwaitRegister(register_action).description();
+            Popup::new(title, self.register_hints(register_action)).render_corner(&self.theme, content_area, frame);
+        }
+        match self.mode {
+            Mode::Finder => self.finder.render(&self.theme, content_area, frame),
+            Mode::Picker => self.picker.render(&self.theme, content_area, frame),
+            Mode::Popup => self.popup.render(&self.theme, content_area, frame),
+            _ => {}
+        }
+    }
+
+    /// Draws the bottom row, as a pending question or three segments:
+    /// the mode or latest message on the left, the position in the selected list in the middle,
+    /// and todo counts on the right. Segments that don't fit are dropped, middle first.
+    fn render_status_bar(&self, area: Rect, frame: &mut Frame) {
+        if let Some(confirm) = &self.confirm {
+            frame.render_widget(confirm.prompt(), area);
+            return;
+        }
+        if let (Mode::Prompt, Some(prompt)) = (self.mode, &self.prompt) {
+            prompt.render(area, frame);
+            return;
+        }
+
+        // Segments
+        let mode_text = match self.config.read_only {
+            true => format!("{} (read-only)", self.mode.name()),
+            false => self.mode.name().to_string(),
+        };
+        let (left, left_color) = match (&self.message, &self.recording) {
+            (Some(message), _) => (message.text.clone(), message.level.color(&self.theme)),
+            (None, Some((register, _))) => (format!("{mode_text} (recording @{register})"), self.theme.fg_unselected),
+            (None, None) => (mode_text, self.theme.fg_unselected),
+        };
+        let middle = match self.selected_todo_list() {
+            Some(todo_list_idx) => {
+                let todo_list = &self.todo_lists[todo_list_idx];
+                match self.selected_todo() {
+                    Some((_, todo_idx)) => format!("{}: item {} of {}", todo_list.name, todo_idx + 1, todo_list.todos.len()),
+                    None => format!("{}: empty", todo_list.name),
+                }
+            }
+            None => String::new(),
+        };
+        let todos = self.todo_lists.iter().flat_map(|todo_list| &todo_list.todos);
+        let total = todos.clone().count();
+        let marked = todos.filter(|todo| todo.marked).count();
+        let modified = match (self.save_failed, self.needs_saving) {
+            (true, _) => " [save failed]",
+            (false, true) => " [+]",
+            (false, false) => "",
+        };
+        let mut right = format!("{total} todos, {marked} marked{modified}");
+        if let Some(clock_format) = &self.config.clock_format {
+            // Invalid formats only fail when written, so this is checked rather than formatted directly.
+            let mut clock = String::new();
+            match write!(clock, "{}", Local::now().format(clock_format)) {
+                Ok(()) => right.push_str(&format!("  {clock}")),
+                Err(_) => right.push_str("  invalid clock_format"),
+            }
+        }
+
+        // Layout, keeping a space between segments
+        let width = area.width as usize;
+        let left_width = left.width();
+        let right_width = match left_width + 1 + right.width() <= width {
+            true => right.width(),
+            false => 0,
         };
-        frame.render_widget(mode_text, bottom_area);
+        let middle_start = width.saturating_sub(middle.width()) / 2;
+        let middle_fits = middle_start > left_width && middle_start + middle.width() < width - right_width;
+        frame.render_widget(Line::from(left).fg(left_color), area);
+
+        // Key hints fill the gap after the mode, dropping whole hints from the right when short of space
+        if self.config.key_hints && self.message.is_none() {
+            let hints_end = match middle_fits {
+                true => middle_start,
+                false => width - right_width,
+            };
+            let available = hints_end.saturating_sub(left_width + 2);
+            let mut hints = String::new();
+            for hint in self.key_hints() {
+                if hints.width() + 1 + hint.width() > available {
+                    break;
+                }
+                hints.push(' ');
+                hints.push_str(&hint);
+            }
+            if !hints.is_empty() {
+                let hints_area = Rect { x: area.x + left_width as u16 + 1, width: hints.width() as u16, ..area };
+                frame.render_widget(Line::from(hints).fg(self.theme.border_unselected), hints_area);
+            }
+        }
+        if right_width > 0 {
+            frame.render_widget(Line::from(right).right_aligned(), area);
+        }
+        if middle_fits {
+            frame.render_widget(Line::from(middle).centered(), area);
+        }
+    }
+
+    /// How the todo list at an index is drawn, scrolled as it was last rendered.
+    fn list_view(&self, todo_list_idx: usize) -> ListView<'_> {
+        let selected_todo_list_idx = self.selection.todo_list.min(self.todo_lists.len().saturating_sub(1));
+        ListView {
+            is_selected: todo_list_idx == selected_todo_list_idx,
+            selection: self.selection,
+            marks: &self.marks,
+            mode: self.mode,
+            scroll: self.scroll_offsets.get(todo_list_idx).copied().unwrap_or(0),
+            wrap: self.config.wrap_todos,
+            line_numbers: self.config.line_numbers,
+            todo_prefix: &self.todo_prefix,
+            theme: &self.theme,
+        }
+    }
+
+    /// Text of the header row, naming the version and the database being edited.
+    /// The database path is shortened to fit `width` cells, first by writing the home directory as ~, then by
+    /// eliding its middle.
+    fn header(&self, width: usize) -> String {
+        let prefix = format!("tdi v{APP_VERSION} — ");
+        let path = contract_tilde(&self.config.dbpath);
+        format!("{prefix}{}", elide_middle(&path, width.saturating_sub(prefix.width())))
+    }
+
+    /// Full name of the selected todo, wrapped to fit `width` cells over at most two rows for the preview strip.
+    /// Takes a row even when nothing is selected, so the lists don't jump as the selection moves.
+    fn preview_lines(&self, width: usize) -> Vec<String> {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return vec![String::new()] };
+        let name = &self.todo_lists[todo_list_idx].todos[todo_idx].name;
+        let rows = wrap_to_width(name, width);
+        let mut lines: Vec<String> = rows
+            .iter()
+            .take(2)
+            .map(|row| name[byte_index(name, row.start)..byte_index(name, row.end)].trim_end().to_string())
+            .collect();
+        if rows.len() > 2 {
+            let rest = &name[byte_index(name, rows[1].start)..];
+            lines[1] = truncate_to_width(rest, width);
+        }
+        lines
+    }
+
+    /// Hints like "o:add" for the most common actions of the current mode, with keys looked up in the key mappings
+    /// so remapped keys show. Actions with no key are left out.
+    fn key_hints(&self) -> Vec<String> {
+        let mut hints = vec![];
+        for (actions, label) in self.mode.key_hints() {
+            let mut keys = vec![];
+            for action in *actions {
+                let key = self.key_mappings
+                    .iter()
+                    .filter(|(key_press, mapped)| key_press.mode == self.mode && *mapped == action)
+                    .map(|(key_press, _)| key_press.hint_name())
+                    .min_by_key(|name| (name.width(), name.chars().any(char::is_uppercase), name.clone())); // Like i over A.
+                keys.extend(key);
+            }
+            if !keys.is_empty() {
+                hints.push(format!("{}:{label}", keys.join("/")));
+            }
+        }
+        hints
     }
 
     /// Index of the currently selected todo list
@@ -196,13 +972,23 @@ pub fn init() -> anyhow::Result<Self> {
         self.selection.todo = todo_idx;
     }
 
+    /// Keeps the selection in bounds after todos are removed.
+    /// The todo that took the selected index is kept selected, else the new last todo.
+    /// When the list becomes empty, the selection rests at index 0 with no todo selected.
+    fn clamp_selection(&mut self) {
+        let Some(last_todo_list_idx) = self.todo_lists.len().checked_sub(1) else { return };
+        self.selection.todo_list = self.selection.todo_list.min(last_todo_list_idx);
+        let todo_list = &self.todo_lists[self.selection.todo_list];
+        self.selection.todo = self.selection.todo.min(todo_list.todos.len().saturating_sub(1));
+    }
+
     /// Indices of the currently selected todo
     fn selected_todo(&self) -> Option<(usize, usize)> {
         if self.todo_lists.is_empty() {
             return None;
         };
         let todo_list_idx = self.selection.todo_list;
-        let todo_list = &self.todo_lists[todo_list_idx];
+        let todo_list = self.todo_lists.get(todo_list_idx)?;
         if todo_list.todos.is_empty() {
             return None;
         };
@@ -210,35 +996,205 @@ pub fn init() -> anyhow::Result<Self> {
         Some((todo_list_idx, todo_idx))
     }
 
+    /// Indices of the currently selected todos.
+    /// In visual mode, this is the range between the anchor and the selected todo.
+    fn selected_todos(&self) -> Option<(usize, Range<usize>)> {
+        let (todo_list_idx, todo_idx) = self.selected_todo()?;
+        if self.mode != Mode::Visual {
+            return Some((todo_list_idx, todo_idx..todo_idx + 1));
+        }
+        let anchor_idx = self.selection.anchor.min(self.todo_lists[todo_list_idx].todos.len() - 1);
+        let start = anchor_idx.min(todo_idx);
+        let end = anchor_idx.max(todo_idx);
+        Some((todo_list_idx, start..end + 1))
+    }
+
     fn set_mode(&mut self, next_mode: Mode) {
+        // An insert session is a single undo step, snapshotted once when it begins and never while typing.
         if next_mode == Mode::Insert {
+            if self.selected_todo().is_none() { return }
+            if self.config.read_only {
+                self.post_message(MessageLevel::Warning, "Read-only: the board can't be changed");
+                return;
+            }
             self.create_snapshot();
         }
         match next_mode {
             Mode::Insert => self.set_mode_insert(),
             Mode::Normal => self.set_mode_normal(),
+            Mode::Finder => self.set_mode_finder(),
+            Mode::Visual => self.set_mode_visual(),
+            Mode::Picker | Mode::Confirm | Mode::Prompt | Mode::Popup => {}
         }
     }
 
+    fn set_mode_visual(&mut self) {
+        let Some((_, todo_idx)) = self.selected_todo() else { return };
+        self.selection.todo = todo_idx;
+        self.selection.anchor = todo_idx;
+        self.mode = Mode::Visual;
+    }
+
+    /// Enters insert mode with the cursor at the end of the selected todo's name.
     fn set_mode_insert(&mut self) {
-        let todo_list = &self.todo_lists[self.selection.todo_list];
-        if todo_list.todos.is_empty() { return }
-        self.selection.char = 0;
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &self.todo_lists[todo_list_idx].todos[todo_idx];
+        self.insert_original = todo.name.clone();
+        self.selection.char = grapheme_count(&todo.name);
         self.mode = Mode::Insert;
     }
 
+    /// Enters insert mode with the cursor at the first non-whitespace character of the selected todo's name.
+    fn insert_start(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        self.set_mode(Mode::Insert);
+        let todo = &self.todo_lists[todo_list_idx].todos[todo_idx];
+        self.selection.char = todo.name.graphemes(true).take_while(|g| g.trim().is_empty()).count();
+    }
+
+    /// Enters insert mode one character right of where the cursor was when the selected todo was last edited.
+    /// If it was not the last todo edited, the cursor is placed at the end of its name.
+    fn append(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        self.set_mode(Mode::Insert);
+        let todo = &self.todo_lists[todo_list_idx].todos[todo_idx];
+        let name_len = grapheme_count(&todo.name);
+        self.selection.char = match self.last_insert {
+            Some(last) if last.todo_list == todo_list_idx && last.todo == todo_idx => (last.char + 1).min(name_len),
+            _ => name_len,
+        };
+    }
+
+    /// Clears the selected todo's name and enters insert mode to retype it.
+    fn change_todo(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        self.set_mode(Mode::Insert);
+        self.todo_lists[todo_list_idx].todos[todo_idx].name.clear();
+        self.selection.char = 0;
+        self.needs_saving = true;
+    }
+
+    /// Finishes editing the selected todo, then starts editing a new todo below it.
+    /// Each todo created this way is its own undo step.
+    fn insert_next(&mut self) {
+        self.set_mode(Mode::Normal);
+        self.add_todo(true);
+    }
+
     fn set_mode_normal(&mut self) {
+        let prev_mode = self.mode;
         self.mode = Mode::Normal;
+        self.prompt = None;
+        if prev_mode != Mode::Insert { return }
+        self.remove_insert_scratch();
         let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
         let todo_list = &mut self.todo_lists[todo_list_idx];
         let todo = &mut todo_list.todos[todo_idx];
         if todo.name.trim().is_empty() {
             todo_list.todos.remove(todo_idx);
-            self.snapshots.pop_back();
+            if self.insert_original.trim().is_empty() {
+                self.discard_snapshot(); // Todo was blank to begin with, so nothing changed.
+            }
+            self.clamp_selection();
+        }
+        else {
+            self.last_insert = Some(self.selection);
+        }
+    }
+
+    fn set_mode_finder(&mut self) {
+        self.finder = Finder::new(&self.todo_lists);
+        self.mode = Mode::Finder;
+    }
+
+    /// Selects the list clicked, and the todo clicked if any. Only works in normal mode.
+    /// Clicking the same todo twice in quick succession edits it, with the cursor where it was clicked.
+    fn click(&mut self, column: u16, row: u16) {
+        if self.mode != Mode::Normal {
+            return;
+        }
+        let is_double_click = matches!(
+            self.last_click,
+            Some((clicked_at, last_column, last_row)) if clicked_at.elapsed() <= DOUBLE_CLICK_TIME && (last_column, last_row) == (column, row)
+        );
+        self.last_click = Some((Instant::now(), column, row));
+        let position = Position::new(column, row);
+        let Some(todo_list_idx) = self.list_areas.iter().position(|area| area.contains(position)) else { return };
+        let Some(todo_list) = self.todo_lists.get(todo_list_idx) else { return };
+        let area = self.list_areas[todo_list_idx];
+        let view = self.list_view(todo_list_idx);
+        let Some(todo_idx) = todo_list.todo_at(&view, area, row) else {
+            self.select_todo_list(todo_list_idx);
+            return;
+        };
+        let char_idx = todo_list.char_at(&view, area, todo_idx, column, row);
+        self.select_todo(todo_list_idx, todo_idx);
+        if is_double_click {
+            self.last_click = None;
+            self.set_mode(Mode::Insert);
+            self.selection.char = char_idx;
+        }
+    }
+
+    /// Scrolls or moves the selection of the list under the pointer, as configured, or moves between lists
+    /// when turned sideways. Turning it anywhere but over a list does nothing. Only works in normal mode.
+    fn wheel(&mut self, column: u16, row: u16, direction: WheelDirection) {
+        if self.mode != Mode::Normal {
+            return;
+        }
+        let position = Position::new(column, row);
+        let Some(todo_list_idx) = self.list_areas.iter().position(|area| area.contains(position)) else { return };
+        if todo_list_idx >= self.todo_lists.len() {
+            return;
+        }
+        let rows = self.config.wheel_rows;
+        match (direction, self.config.wheel) {
+            (WheelDirection::Left, _) => self.move_left(),
+            (WheelDirection::Right, _) => self.move_right(),
+            (WheelDirection::Up, WheelAction::Select) => {
+                self.select_todo_list(todo_list_idx);
+                self.move_up_by(rows);
+            }
+            (WheelDirection::Down, WheelAction::Select) => {
+                self.select_todo_list(todo_list_idx);
+                self.move_down_by(rows);
+            }
+            (WheelDirection::Up, WheelAction::Scroll) => self.scroll_list(todo_list_idx, -(rows as isize)),
+            (WheelDirection::Down, WheelAction::Scroll) => self.scroll_list(todo_list_idx, rows as isize),
+        }
+    }
+
+    /// Scrolls a list by some number of rows, down if positive. If the list is selected and its selected todo
+    /// leaves the view, the todo at the nearest edge of the view is selected instead.
+    fn scroll_list(&mut self, todo_list_idx: usize, rows: isize) {
+        let area = self.list_areas[todo_list_idx];
+        let todo_list = &self.todo_lists[todo_list_idx];
+        let mut view = self.list_view(todo_list_idx);
+        view.scroll = view.scroll.saturating_add_signed(rows);
+        view.is_selected = false; // Only clamps the scroll, rather than bringing the selection back into view.
+        let scroll = todo_list.scroll_into_view(&view, area);
+        view.scroll = scroll;
+        let visible = area.height.saturating_sub(2);
+        let first_shown = todo_list.todo_at(&view, area, area.y + 1);
+        let last_shown = todo_list.todo_at(&view, area, area.y + visible).or(todo_list.todos.len().checked_sub(1));
+        self.scroll_offsets[todo_list_idx] = scroll;
+        let Some((selected_list_idx, todo_idx)) = self.selected_todo() else { return };
+        if selected_list_idx != todo_list_idx {
+            return;
+        }
+        match (first_shown, last_shown) {
+            (Some(first_shown), _) if todo_idx < first_shown => self.select_todo(todo_list_idx, first_shown),
+            (_, Some(last_shown)) if todo_idx > last_shown => self.select_todo(todo_list_idx, last_shown),
+            _ => {}
         }
-        if self.selection.todo > 0 {
-            self.selection.todo -= 1;
+    }
+
+    /// Selects the todo chosen in the finder, then returns to normal mode.
+    fn finder_jump(&mut self) {
+        if let Some((todo_list_idx, todo_idx)) = self.finder.selected_todo() {
+            self.select_todo(todo_list_idx, todo_idx);
         }
+        self.mode = Mode::Normal;
     }
 
     fn move_left(&mut self) {
@@ -246,6 +1202,9 @@ pub fn init() -> anyhow::Result<Self> {
             return;
         };
         if todo_list_idx == 0 {
+            if self.config.wrap_navigation {
+                self.select_todo_list(self.todo_lists.len() - 1);
+            }
             return;
         };
         self.select_todo_list(todo_list_idx - 1);
@@ -255,6 +1214,12 @@ pub fn init() -> anyhow::Result<Self> {
         let Some(todo_list_idx) = self.selected_todo_list() else {
             return;
         };
+        if todo_list_idx == self.todo_lists.len() - 1 {
+            if self.config.wrap_navigation {
+                self.select_todo_list(0);
+            }
+            return;
+        };
         self.select_todo_list(todo_list_idx + 1);
     }
 
@@ -263,6 +1228,10 @@ pub fn init() -> anyhow::Result<Self> {
             return;
         };
         if todo_idx == 0 {
+            if self.config.wrap_navigation {
+                let todo_list = &self.todo_lists[todo_list_idx];
+                self.select_todo(todo_list_idx, todo_list.todos.len() - 1);
+            }
             return;
         };
         self.select_todo(todo_list_idx, todo_idx - 1);
@@ -272,32 +1241,49 @@ pub fn init() -> anyhow::Result<Self> {
         let Some((todo_list_idx, todo_idx)) = self.selected_todo() else {
             return;
         };
+        let todo_list = &self.todo_lists[todo_list_idx];
+        if todo_idx == todo_list.todos.len() - 1 {
+            if self.config.wrap_navigation {
+                self.select_todo(todo_list_idx, 0);
+            }
+            return;
+        };
         self.select_todo(todo_list_idx, todo_idx + 1);
     }
 
     fn move_up_half(&mut self) {
+        self.move_up_by(self.list_height as usize / 2);
+    }
+
+    fn move_down_half(&mut self) {
+        self.move_down_by(self.list_height as usize / 2);
+    }
+
+    fn move_page_up(&mut self) {
+        self.move_up_by(self.list_height as usize);
+    }
+
+    fn move_page_down(&mut self) {
+        self.move_down_by(self.list_height as usize);
+    }
+
+    /// Moves the selection up by some number of todos, stopping at the first.
+    fn move_up_by(&mut self, amount: usize) {
         let Some((todo_list_idx, todo_idx)) = self.selected_todo() else {
             return;
         };
-        let next_todo_idx = if todo_idx > MOVE_HALF_AMOUNT {
-            todo_idx - MOVE_HALF_AMOUNT
-        }
-        else {
-            0
-        };
+        let next_todo_idx = todo_idx.saturating_sub(amount.max(1));
         self.select_todo(todo_list_idx, next_todo_idx);
     }
 
-    fn move_down_half(&mut self) {
+    /// Moves the selection down by some number of todos, stopping at the last.
+    fn move_down_by(&mut self, amount: usize) {
         let Some((todo_list_idx, todo_idx)) = self.selected_todo() else {
             return;
         };
         let todo_list = &self.todo_lists[todo_list_idx];
-        let last_todo_idx = match todo_list.todos.len() {
-            0 => return,
-            len => len-1,
-        };
-        let next_todo_idx = (todo_idx + MOVE_HALF_AMOUNT).min(last_todo_idx);
+        let last_todo_idx = todo_list.todos.len() - 1;
+        let next_todo_idx = (todo_idx + amount.max(1)).min(last_todo_idx);
         self.select_todo(todo_list_idx, next_todo_idx);
     }
 
@@ -325,79 +1311,240 @@ pub fn init() -> anyhow::Result<Self> {
             return;
         };
         self.create_snapshot();
-        self.set_mode_insert();
         let todo_list = &mut self.todo_lists[self.selection.todo_list];
         let todos = &mut todo_list.todos;
         let todo_idx = match below {
             false => self.selection.todo.min(todos.len()),
             true => (self.selection.todo + 1).min(todos.len()),
         };
-        todos.insert(todo_idx, Todo::new(""));
+        todos.insert(todo_idx, Todo { id: self.next_todo_id, ..Todo::new("") });
+        self.next_todo_id += 1;
         self.selection.todo = todo_idx;
+        self.set_mode_insert();
         self.needs_saving = true;
     }
     
+    /// Toggles the mark of the selected todos.
+    /// When several are selected, all are marked unless all already are, in which case all are unmarked.
     fn toggle_mark(&mut self) {
-        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let Some((todo_list_idx, todo_range)) = self.selected_todos() else { return };
+        self.create_snapshot();
+        let todos = &mut self.todo_lists[todo_list_idx].todos[todo_range];
+        let marked = !todos.iter().all(|todo| todo.marked);
+        for todo in todos {
+            todo.marked = marked;
+        }
+        self.needs_saving = true;
+        self.mode = Mode::Normal;
+    }
+
+    /// Removes the selected [`Todo`]s, first asking if any would be deleted rather than moved to the backlog.
+    fn confirm_delete_todo(&mut self) {
+        let Some((todo_list_idx, todo_range)) = self.selected_todos() else { return };
+        self.mode = Mode::Normal;
+        let todo_list = &self.todo_lists[todo_list_idx];
+        let deleted: Vec<&Todo> = todo_list.todos[todo_range.clone()].iter().filter(|todo| !todo.marked).collect();
+        if !self.config.confirm_delete || deleted.is_empty() {
+            self.delete_todos(todo_list_idx, todo_range);
+            return;
+        }
+        self.confirm = Some(Confirm::DeleteTodos {
+            todo_list: todo_list_idx,
+            todos: todo_range,
+            first_name: deleted[0].name.clone(),
+            count: deleted.len(),
+        });
+        self.mode = Mode::Confirm;
+    }
+
+    /// Removes a range of [`Todo`]s from a todo list.
+    /// Unmarked todos are deleted, while marked todos are moved to the backlog.
+    fn delete_todos(&mut self, todo_list_idx: usize, todo_range: Range<usize>) {
+        let Some(todo_list) = self.todo_lists.get(todo_list_idx) else { return };
+        if todo_range.end > todo_list.todos.len() {
+            return;
+        }
+        let is_backlog = todo_list_idx == BACKLOG_LIST_IDX;
+        if is_backlog && todo_list.todos[todo_range.clone()].iter().all(|todo| todo.marked) {
+            return;
+        }
         self.create_snapshot();
         let todo_list = &mut self.todo_lists[todo_list_idx];
-        let todo = &mut todo_list.todos[todo_idx];
-        todo.marked = !todo.marked;
+        let removed: Vec<Todo> = todo_list.todos.drain(todo_range.clone()).collect();
+        let mut kept = vec![];
+        for todo in removed {
+            if !todo.marked {
+                continue;
+            }
+            match is_backlog {
+                false => self.todo_lists[BACKLOG_LIST_IDX].todos.push(todo),
+                true => kept.push(todo),
+            }
+        }
+        let todo_list = &mut self.todo_lists[todo_list_idx];
+        todo_list.todos.splice(todo_range.start..todo_range.start, kept);
+        self.selection.todo = todo_range.start;
+        self.clamp_selection();
+        self.prune_marks();
         self.needs_saving = true;
     }
 
-    /// Removes the currently selected [`Todo`]
-    fn delete_todo(&mut self) {
+    /// Gives an id to every todo without one, like those loaded from databases saved before todos had ids.
+    fn assign_todo_ids(&mut self) {
+        let max_id = self.todo_lists.iter().flat_map(|todo_list| &todo_list.todos).map(|todo| todo.id).max();
+        self.next_todo_id = self.next_todo_id.max(max_id.unwrap_or(0) + 1);
+        for todo in self.todo_lists.iter_mut().flat_map(|todo_list| &mut todo_list.todos) {
+            if todo.id == 0 {
+                todo.id = self.next_todo_id;
+                self.next_todo_id += 1;
+            }
+        }
+    }
+
+    /// Indices of the todo with the id given, wherever it is.
+    fn find_todo(&self, id: u64) -> Option<(usize, usize)> {
+        self.todo_lists.iter().enumerate().find_map(|(todo_list_idx, todo_list)| {
+            let todo_idx = todo_list.todos.iter().position(|todo| todo.id == id)?;
+            Some((todo_list_idx, todo_idx))
+        })
+    }
+
+    /// Bookmarks the selected todo under a letter.
+    fn set_mark(&mut self, mark: char) {
         let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
-        let todo_list = &mut self.todo_lists[todo_list_idx];
-        let todo = &mut todo_list.todos[todo_idx];
-        if !todo.marked {
-            self.create_snapshot();
-            let todo_list = &mut self.todo_lists[todo_list_idx];
-            todo_list.todos.remove(todo_idx);
-            self.needs_saving = true;
+        let id = self.todo_lists[todo_list_idx].todos[todo_idx].id;
+        self.marks.insert(mark, id);
+        self.needs_saving = true;
+    }
+
+    /// Selects the todo bookmarked under a letter, wherever it has moved to.
+    fn jump_to_mark(&mut self, mark: char) {
+        let Some(id) = self.marks.get(&mark) else { return };
+        if let Some((todo_list_idx, todo_idx)) = self.find_todo(*id) {
+            self.select_todo(todo_list_idx, todo_idx);
         }
-        else if todo_list_idx != BACKLOG_LIST_IDX {
-            self.create_snapshot();
-            let todo_list = &mut self.todo_lists[todo_list_idx];
-            let todo = todo_list.todos.remove(todo_idx);
-            let backlog_todo_list = &mut self.todo_lists[BACKLOG_LIST_IDX];
-            backlog_todo_list.todos.push(todo);
-            self.needs_saving = true;
+    }
+
+    /// Forgets marks of todos that no longer exist.
+    fn prune_marks(&mut self) {
+        let marks = std::mem::take(&mut self.marks);
+        self.marks = marks.into_iter().filter(|(_, id)| self.find_todo(*id).is_some()).collect();
+    }
+
+    /// Asks to delete every marked todo in the selected todo list.
+    fn confirm_delete_marked(&mut self) {
+        let Some(todo_list_idx) = self.selected_todo_list() else { return };
+        let todo_list = &self.todo_lists[todo_list_idx];
+        let count = todo_list.todos.iter().filter(|todo| todo.marked).count();
+        if count == 0 {
+            return;
+        }
+        self.confirm = Some(Confirm::DeleteMarked {
+            todo_list: todo_list_idx,
+            todo_list_name: todo_list.name.clone(),
+            count,
+        });
+        self.mode = Mode::Confirm;
+    }
+
+    /// Deletes every marked todo in a todo list, rather than moving them to the backlog.
+    fn delete_marked(&mut self, todo_list_idx: usize) {
+        if todo_list_idx >= self.todo_lists.len() {
+            return;
+        }
+        self.create_snapshot();
+        self.todo_lists[todo_list_idx].todos.retain(|todo| !todo.marked);
+        self.clamp_selection();
+        self.prune_marks();
+        self.needs_saving = true;
+    }
+
+    /// Sorts the selected todo list, cycling to the next sort order on repeated presses.
+    /// Selection stays on the same todo.
+    fn sort_list(&mut self) {
+        let Some(todo_list_idx) = self.selected_todo_list() else { return };
+        let sort_order = match self.sort_order {
+            Some((sorted_idx, sort_order)) if sorted_idx == todo_list_idx => sort_order.next(),
+            _ => SortOrder::Alphabetical,
+        };
+        self.sort_order = Some((todo_list_idx, sort_order));
+        self.create_snapshot();
+        let todos = &mut self.todo_lists[todo_list_idx].todos;
+        let mut order: Vec<usize> = (0..todos.len()).collect();
+        match sort_order {
+            SortOrder::Alphabetical => order.sort_by_cached_key(|&i| todos[i].name.to_lowercase()),
+            SortOrder::MarkedLast => order.sort_by_key(|&i| todos[i].marked),
         }
+        if let Some(todo_idx) = order.iter().position(|&i| i == self.selection.todo) {
+            self.selection.todo = todo_idx;
+        }
+        let mut unsorted: Vec<Option<Todo>> = todos.drain(..).map(Some).collect();
+        todos.extend(order.into_iter().filter_map(|i| unsorted[i].take()));
+        self.needs_saving = true;
     }
 
     fn move_todo_left(&mut self) {
-        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else {
+        let Some(todo_list_idx) = self.selected_todo_list() else {
             return;
         };
         if todo_list_idx == 0 {
             return;
         };
-        self.create_snapshot();
-        let todo_list = &mut self.todo_lists[todo_list_idx];
-        let todo = todo_list.todos.remove(todo_idx);
-        let next_todo_list = &mut self.todo_lists[todo_list_idx - 1];
-        let next_todo_idx = self.selection.todo.min(next_todo_list.todos.len());
-        next_todo_list.todos.insert(next_todo_idx, todo);
-        self.selection.todo_list -= 1;
-        self.needs_saving = true;
+        self.move_todos_to_list(todo_list_idx - 1);
     }
 
     fn move_todo_right(&mut self) {
-        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else {
+        let Some(todo_list_idx) = self.selected_todo_list() else {
             return;
         };
-        if todo_list_idx == self.todo_lists.len() - 1 {
+        self.move_todos_to_list(todo_list_idx + 1);
+    }
+
+    /// Moves the selected todos to another todo list, preserving their order.
+    /// Selection follows the moved todos.
+    fn move_todos_to_list(&mut self, next_todo_list_idx: usize) {
+        let Some((todo_list_idx, todo_range)) = self.selected_todos() else {
+            return;
+        };
+        if next_todo_list_idx >= self.todo_lists.len() {
             return;
         };
         self.create_snapshot();
         let todo_list = &mut self.todo_lists[todo_list_idx];
-        let todo = todo_list.todos.remove(todo_idx);
-        let next_todo_list = &mut self.todo_lists[todo_list_idx + 1];
-        let next_todo_idx = self.selection.todo.min(next_todo_list.todos.len());
-        next_todo_list.todos.insert(next_todo_idx, todo);
-        self.selection.todo_list += 1;
+        let todos: Vec<Todo> = todo_list.todos.drain(todo_range.clone()).collect();
+        let next_todo_list = &mut self.todo_lists[next_todo_list_idx];
+        let next_todo_idx = todo_range.start.min(next_todo_list.todos.len());
+        next_todo_list.todos.splice(next_todo_idx..next_todo_idx, todos);
+        self.selection.todo_list = next_todo_list_idx;
+        self.selection.todo = next_todo_idx;
+        self.mode = Mode::Normal;
+        self.needs_saving = true;
+    }
+
+    /// Opens a picker for choosing the todo list to send the selected todo to.
+    fn open_send_picker(&mut self) {
+        let Some((todo_list_idx, _)) = self.selected_todo() else { return };
+        let names = self.todo_lists.iter().map(|todo_list| todo_list.name.clone()).collect();
+        self.picker = Picker::new("Send to", names, todo_list_idx);
+        self.mode = Mode::Picker;
+    }
+
+    /// Appends the selected todo to the end of another todo list, then returns to normal mode.
+    fn send_todo(&mut self, next_todo_list_idx: usize) {
+        self.mode = Mode::Normal;
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        if next_todo_list_idx >= self.todo_lists.len() {
+            return;
+        }
+        self.create_snapshot();
+        let todo = self.todo_lists[todo_list_idx].todos.remove(todo_idx);
+        let next_todo_list = &mut self.todo_lists[next_todo_list_idx];
+        next_todo_list.todos.push(todo);
+        if self.config.follow_sent_todo {
+            self.selection.todo_list = next_todo_list_idx;
+            self.selection.todo = next_todo_list.todos.len() - 1;
+        }
+        self.clamp_selection();
         self.needs_saving = true;
     }
 
@@ -430,46 +1577,374 @@ pub fn init() -> anyhow::Result<Self> {
         self.needs_saving = true;
     }
 
-    /// Inputs a character to the name of the currently selected [`Todo`].
-    fn input(&mut self, code: KeyCode) {
-        if self.todo_lists.is_empty() {
+    /// Moves the selected todo to the top of its todo list.
+    fn move_todo_top(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else {
             return;
         };
-        let todo_list = &mut self.todo_lists[self.selection.todo_list];
-        let todos = &mut todo_list.todos;
-        if todos.is_empty() {
+        if todo_idx == 0 {
+            return;
+        };
+        self.create_snapshot();
+        let todos = &mut self.todo_lists[todo_list_idx].todos;
+        let todo = todos.remove(todo_idx);
+        todos.insert(0, todo);
+        self.select_todo(todo_list_idx, 0);
+        self.needs_saving = true;
+    }
+
+    /// Moves the selected todo to the bottom of its todo list.
+    fn move_todo_bottom(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else {
             return;
         };
-        let todo_idx = self.selection.todo.min(todos.len() - 1);
-        let todo = &mut todos[todo_idx];
-        let char_index = self.selection.char;
+        let last_todo_idx = self.todo_lists[todo_list_idx].todos.len() - 1;
+        if todo_idx == last_todo_idx {
+            return;
+        };
+        self.create_snapshot();
+        let todos = &mut self.todo_lists[todo_list_idx].todos;
+        let todo = todos.remove(todo_idx);
+        todos.push(todo);
+        self.select_todo(todo_list_idx, last_todo_idx);
+        self.needs_saving = true;
+    }
+
+    /// Inputs a character to the name of the currently selected [`Todo`], or to the finder's query or the prompt.
+    fn input(&mut self, code: KeyCode) {
+        if self.mode == Mode::Finder {
+            match code {
+                KeyCode::Char(c) => self.finder.push(c),
+                KeyCode::Backspace => self.finder.pop(),
+                _ => {}
+            }
+            return;
+        }
+        if let (Mode::Prompt, Some(prompt)) = (self.mode, &mut self.prompt) {
+            match code {
+                KeyCode::Char(c) => prompt.text.push(c),
+                KeyCode::Backspace => {
+                    prompt.text.pop();
+                }
+                _ => {}
+            }
+            return;
+        }
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &mut self.todo_lists[todo_list_idx].todos[todo_idx];
+        let name_len = grapheme_count(&todo.name);
+        let char_index = self.selection.char.min(name_len);
         match code {
             KeyCode::Char(c) => {
-                todo.name.insert(char_index, c);
-                self.selection.char += 1;
+                // Combining characters join the grapheme before them, so the cursor is recomputed.
+                let byte_idx = byte_index(&todo.name, char_index);
+                todo.name.insert(byte_idx, c);
+                self.selection.char = grapheme_count(&todo.name[..byte_idx + c.len_utf8()]);
             }
-            KeyCode::Backspace => {
-                if self.selection.char > 0 {
-                    todo.name.remove(char_index - 1);
-                    self.selection.char -= 1;
-                }
+            KeyCode::Backspace if char_index > 0 => {
+                let byte_range = byte_index(&todo.name, char_index - 1)..byte_index(&todo.name, char_index);
+                todo.name.replace_range(byte_range, "");
+                self.selection.char = char_index - 1;
             }
-            KeyCode::Delete => {
-                if self.selection.char < todo.name.len() {
-                    todo.name.remove(char_index);
-                }
+            KeyCode::Delete if char_index < name_len => {
+                let byte_range = byte_index(&todo.name, char_index)..byte_index(&todo.name, char_index + 1);
+                todo.name.replace_range(byte_range, "");
             }
             _ => {}
         }
         self.needs_saving = true;
+        self.schedule_insert_scratch();
     }
 
-    fn move_cursor_right(&mut self) {
-        let Some(todo_list) = self.todo_lists.get(self.selection.todo_list) else {
+    /// Opens a popup listing every key binding by mode, with keys doing the same thing on one line.
+    fn show_help(&mut self) {
+        let mut lines = vec![];
+        for mode in Mode::ALL {
+            let mut bindings: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+            for (key_press, action) in &self.key_mappings {
+                if key_press.mode == mode {
+                    bindings.entry(action.description()).or_default().push(key_press.key_name());
+                }
+            }
+            if bindings.is_empty() {
+                continue;
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!("{} mode", mode.name()));
+            for (description, mut keys) in bindings {
+                keys.sort_by(|a, b| (a.len(), a).cmp(&(b.len(), b))); // Single keys first.
+                keys.dedup();
+                lines.push(format!("  {:<20} {description}", keys.join(", ")));
+            }
+        }
+        self.popup = Popup::new("Help", lines);
+        self.mode = Mode::Popup;
+    }
+
+    /// Opens a popup showing everything about the selected todo, including the full name cut short in its list.
+    fn show_detail(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo_list = &self.todo_lists[todo_list_idx];
+        let todo = &todo_list.todos[todo_idx];
+        let mut lines = vec![
+            todo.name.clone(),
+            String::new(),
+            format!("List: {} (item {} of {})", todo_list.name, todo_idx + 1, todo_list.todos.len()),
+            format!("Marked: {}", if todo.marked { "yes" } else { "no" }),
+        ];
+        let marks: Vec<String> = self.marks.iter().filter(|(_, id)| **id == todo.id).map(|(mark, _)| mark.to_string()).collect();
+        if !marks.is_empty() {
+            lines.push(format!("Bookmarks: {}", marks.join(", ")));
+        }
+        self.popup = Popup::new("Todo", lines);
+        self.mode = Mode::Popup;
+    }
+
+    /// Copies the names of the selected todos to the system clipboard, one per line.
+    fn yank_to_clipboard(&mut self) {
+        let Some((todo_list_idx, todo_range)) = self.selected_todos() else { return };
+        let todos = &self.todo_lists[todo_list_idx].todos[todo_range];
+        let text = todos.iter().map(|todo| todo.name.as_str()).collect::<Vec<_>>().join("\n");
+        let count = todos.len();
+        match set_clipboard_text(text) {
+            Ok(()) if count == 1 => self.post_message(MessageLevel::Info, "Yanked 1 todo"),
+            Ok(()) => self.post_message(MessageLevel::Info, format!("Yanked {count} todos")),
+            Err(err) => self.post_message(MessageLevel::Error, format!("Could not yank: {err}")),
+        }
+        if self.mode == Mode::Visual {
+            self.set_mode(Mode::Normal);
+        }
+    }
+
+    /// Pastes the system clipboard.
+    /// In insert mode the text goes at the cursor, with line breaks turned into spaces.
+    /// Otherwise each non-blank line becomes a new todo below the selection.
+    fn paste_clipboard(&mut self) {
+        let text = match get_clipboard_text() {
+            Ok(text) => text,
+            Err(err) => {
+                self.post_message(MessageLevel::Error, format!("Could not paste: {err}"));
+                return;
+            }
+        };
+        match self.mode {
+            Mode::Insert => self.paste_text(&text),
+            _ => self.paste_todos(&text),
+        }
+    }
+
+    /// Inserts text at the cursor of the todo being edited.
+    fn paste_text(&mut self, text: &str) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let text: String = text
+            .lines()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect();
+        if text.is_empty() {
+            self.post_message(MessageLevel::Warning, "Nothing to paste");
+            return;
+        }
+        let todo = &mut self.todo_lists[todo_list_idx].todos[todo_idx];
+        let byte_idx = byte_index(&todo.name, self.selection.char);
+        todo.name.insert_str(byte_idx, &text);
+        self.selection.char = grapheme_count(&todo.name[..byte_idx + text.len()]);
+        self.needs_saving = true;
+        self.schedule_insert_scratch();
+    }
+
+    /// Adds a todo below the selection for each non-blank line of text, selecting the last one.
+    fn paste_todos(&mut self, text: &str) {
+        let Some(todo_list_idx) = self.selected_todo_list() else { return };
+        let names: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        if names.is_empty() {
+            self.post_message(MessageLevel::Warning, "Nothing to paste");
+            return;
+        }
+        self.create_snapshot();
+        let todos = &mut self.todo_lists[todo_list_idx].todos;
+        let mut todo_idx = match todos.is_empty() {
+            true => 0,
+            false => (self.selection.todo + 1).min(todos.len()),
+        };
+        for name in names {
+            let name: String = name.chars().filter(|c| !c.is_control()).collect();
+            todos.insert(todo_idx, Todo { id: self.next_todo_id, ..Todo::new(&name) });
+            self.next_todo_id += 1;
+            todo_idx += 1;
+        }
+        self.selection.todo = todo_idx - 1;
+        self.needs_saving = true;
+    }
+
+    /// Moves the todo being edited to an adjacent todo list without ending the insert session.
+    /// The session's snapshot already covers the move, so one undo reverts both.
+    fn move_editing_todo(&mut self, right: bool) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let next_todo_list_idx = match right {
+            false if todo_list_idx > 0 => todo_list_idx - 1,
+            true if todo_list_idx + 1 < self.todo_lists.len() => todo_list_idx + 1,
+            _ => return,
+        };
+        let todo = self.todo_lists[todo_list_idx].todos.remove(todo_idx);
+        let next_todo_list = &mut self.todo_lists[next_todo_list_idx];
+        let next_todo_idx = todo_idx.min(next_todo_list.todos.len());
+        next_todo_list.todos.insert(next_todo_idx, todo);
+        self.selection.todo_list = next_todo_list_idx;
+        self.selection.todo = next_todo_idx;
+        self.needs_saving = true;
+        self.schedule_insert_scratch();
+    }
+
+    /// Deletes from the cursor back to the start of the previous word.
+    fn delete_word_backward(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &mut self.todo_lists[todo_list_idx].todos[todo_idx];
+        let end = self.selection.char.min(grapheme_count(&todo.name));
+        let start = prev_word_start(&todo.name, end);
+        if start == end {
+            return;
+        }
+        let byte_range = byte_index(&todo.name, start)..byte_index(&todo.name, end);
+        todo.name.replace_range(byte_range, "");
+        self.selection.char = start;
+        self.needs_saving = true;
+        self.schedule_insert_scratch();
+    }
+
+    /// Deletes everything before the cursor.
+    fn delete_to_start(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &mut self.todo_lists[todo_list_idx].todos[todo_idx];
+        if self.selection.char == 0 {
+            return;
+        }
+        let end = byte_index(&todo.name, self.selection.char);
+        todo.name.replace_range(..end, "");
+        self.selection.char = 0;
+        self.needs_saving = true;
+        self.schedule_insert_scratch();
+    }
+
+    /// Deletes everything after the cursor.
+    fn delete_to_end(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &mut self.todo_lists[todo_list_idx].todos[todo_idx];
+        let start = byte_index(&todo.name, self.selection.char);
+        if start == todo.name.len() {
             return;
+        }
+        todo.name.truncate(start);
+        self.needs_saving = true;
+        self.schedule_insert_scratch();
+    }
+
+    /// Path of the scratch file holding the in-progress insert buffer.
+    fn insert_scratch_path(&self) -> String {
+        format!("{}.insert", self.config.dbpath)
+    }
+
+    /// Writes the todo being edited to the scratch file on the next tick at least a moment from now,
+    /// so a burst of typing is written once rather than on every key press.
+    fn schedule_insert_scratch(&mut self) {
+        self.insert_scratch_due.get_or_insert_with(|| Instant::now() + INSERT_SCRATCH_DELAY);
+    }
+
+    /// Writes the todo being edited to the scratch file, so it can be recovered after a crash.
+    /// This is best effort, as failing to write it should not interrupt typing.
+    fn write_insert_scratch(&mut self) {
+        self.insert_scratch_due = None;
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let scratch = InsertScratch {
+            todo_list: todo_list_idx,
+            todo: todo_idx,
+            original: self.insert_original.clone(),
+            name: self.todo_lists[todo_list_idx].todos[todo_idx].name.clone(),
+            char: self.selection.char,
         };
-        let todo = &todo_list.todos[self.selection.todo];
-        if self.selection.char >= todo.name.len() {
+        if let Ok(scratch_str) = serde_yaml::to_string(&scratch) {
+            let _ = std::fs::write(self.insert_scratch_path(), scratch_str);
+        }
+    }
+
+    fn remove_insert_scratch(&mut self) {
+        self.insert_scratch_due = None;
+        let _ = std::fs::remove_file(self.insert_scratch_path());
+    }
+
+    /// Loads the scratch file left behind by a session that ended mid-insert, if any.
+    /// Unreadable scratch files are discarded.
+    fn load_insert_scratch(&mut self) -> Option<InsertScratch> {
+        let scratch_str = std::fs::read_to_string(self.insert_scratch_path()).ok()?;
+        let scratch = serde_yaml::from_str(&scratch_str).ok();
+        if scratch.is_none() {
+            self.remove_insert_scratch();
+        }
+        scratch
+    }
+
+    /// Restores a recovered insert buffer into the todo it was typed in, so `a` resumes where typing stopped.
+    /// If that todo no longer matches, the text is restored as a new todo instead.
+    fn restore_insert_scratch(&mut self, scratch: InsertScratch) {
+        self.remove_insert_scratch();
+        if self.todo_lists.is_empty() {
+            return;
+        }
+        self.create_snapshot();
+        let todo_list_idx = scratch.todo_list.min(self.todo_lists.len() - 1);
+        let todos = &mut self.todo_lists[todo_list_idx].todos;
+        let todo_idx = scratch.todo.min(todos.len());
+        match todos.get_mut(todo_idx) {
+            Some(todo) if todo.name == scratch.original => todo.name = scratch.name,
+            _ => {
+                todos.insert(todo_idx, Todo { id: self.next_todo_id, ..Todo::new(scratch.name) });
+                self.next_todo_id += 1;
+            }
+        }
+        self.select_todo(todo_list_idx, todo_idx);
+        self.last_insert = Some(Selection { char: scratch.char, ..self.selection });
+        self.needs_saving = true;
+    }
+
+    /// Answers the pending yes/no question.
+    fn answer_confirm(&mut self, yes: bool) -> anyhow::Result<()> {
+        self.mode = Mode::Normal;
+        let Some(confirm) = self.confirm.take() else { return Ok(()) };
+        match confirm {
+            Confirm::RestoreInsert(scratch) => {
+                match yes {
+                    true => self.restore_insert_scratch(scratch),
+                    false => self.remove_insert_scratch(),
+                }
+                if !self.popup.lines.is_empty() {
+                    self.mode = Mode::Popup; // Held back by the question when starting.
+                }
+            }
+            Confirm::DeleteMarked { todo_list, .. } if yes => self.delete_marked(todo_list),
+            Confirm::DeleteMarked { .. } => {}
+            Confirm::DeleteTodos { todo_list, todos, .. } if yes => self.delete_todos(todo_list, todos),
+            Confirm::DeleteTodos { .. } => {}
+            Confirm::ForceQuit if yes => self.force_quit(),
+            Confirm::ForceQuit => {}
+            Confirm::Reload | Confirm::ChangedOnDisk if yes => self.reload()?,
+            Confirm::Reload => {}
+            Confirm::ChangedOnDisk => {
+                self.post_message(MessageLevel::Warning, "Kept your changes, which replace the database on disk once saved");
+            }
+            Confirm::OverwriteExport(export_path) if yes => self.export_board(&export_path)?,
+            Confirm::OverwriteExport(_) => {}
+        }
+        Ok(())
+    }
+
+    fn move_cursor_right(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &self.todo_lists[todo_list_idx].todos[todo_idx];
+        if self.selection.char >= grapheme_count(&todo.name) {
             return;
         };
         self.selection.char += 1;
@@ -486,98 +1961,695 @@ pub fn init() -> anyhow::Result<Self> {
         self.selection.char = 0;
     }
 
+    fn move_cursor_word_forward(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &self.todo_lists[todo_list_idx].todos[todo_idx];
+        self.selection.char = next_word_start(&todo.name, self.selection.char);
+    }
+
+    fn move_cursor_word_backward(&mut self) {
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &self.todo_lists[todo_list_idx].todos[todo_idx];
+        self.selection.char = prev_word_start(&todo.name, self.selection.char);
+    }
+
     fn move_cursor_end(&mut self) {
-        let Some(todo_list) = self.todo_lists.get(self.selection.todo_list) else {
-            return;
-        };
-        let todo = &todo_list.todos[self.selection.todo];
-        self.selection.char = todo.name.len();
+        let Some((todo_list_idx, todo_idx)) = self.selected_todo() else { return };
+        let todo = &self.todo_lists[todo_list_idx].todos[todo_idx];
+        self.selection.char = grapheme_count(&todo.name);
+    }
+
+    /// Format the database is saved in, as configured or going by its extension.
+    fn db_format(&self) -> DbFormat {
+        self.config.db_format.unwrap_or_else(|| DbFormat::for_path(&self.config.dbpath))
     }
 
+    /// Saves the database if anything changed, remembering if it failed so the status bar can say so.
     fn save(&mut self) -> anyhow::Result<()> {
-        if !self.needs_saving {
+        if !self.needs_saving || self.config.read_only {
             return Ok(());
         }
+        let result = self.write_db();
+        self.save_failed = result.is_err();
+        result
+    }
+
+    fn write_db(&mut self) -> anyhow::Result<()> {
         let dbpath = Path::new(&self.config.dbpath);
         if let Some(parent) = dbpath.parent() {
             std::fs::create_dir_all(parent)?;
         }
         let state = State::create(self);
-        let state_str = serde_yaml::to_string(&state)?;
-        std::fs::write(dbpath, state_str)?;
+        let state_hash = state_hash(&state);
+        if self.saved_hash != Some(state_hash) {
+            if let Err(err) = self.rotate_backups() {
+                self.post_message(MessageLevel::Warning, format!("Could not back up the database: {err:#}"));
+            }
+        }
+        self.db_format().storage(&self.config.dbpath).save(&state)?;
         self.needs_saving = false;
+        self.saved_hash = Some(state_hash);
+        self.db_stamp = self.read_db_stamp();
+        if self.config.git_autocommit {
+            start_git_commit(&self.config.dbpath);
+        }
         Ok(())
     }
 
-    fn undo(&mut self) {
-        if self.current_snapshot == 0 { return };
-        self.current_snapshot -= 1;
-        let mut snapshot = State::create(self);
-        std::mem::swap(&mut snapshot, &mut self.snapshots[self.current_snapshot]);
-        snapshot.restore(self);
-        self.needs_saving = true;
+    /// Copies the database about to be replaced to the first of `backup_count` backups, like db.yml.1,
+    /// after shifting older backups along and dropping those past the count.
+    /// Does nothing if there is no database yet.
+    fn rotate_backups(&self) -> anyhow::Result<()> {
+        let count = self.config.backup_count;
+        let dbpath = Path::new(&self.config.dbpath);
+        if count == 0 || !dbpath.exists() {
+            return Ok(());
+        }
+        let file_name = dbpath.file_name().context("Database path has no file name")?.to_string_lossy();
+        let backup_dir = match &self.config.backup_dir {
+            Some(backup_dir) => PathBuf::from(expand_tilde(backup_dir)),
+            None => dbpath.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        std::fs::create_dir_all(&backup_dir)?;
+        let backup_path = |n: usize| backup_dir.join(format!("{file_name}.{n}"));
+        let mut n = count;
+        while backup_path(n + 1).exists() {
+            n += 1;
+        }
+        while n > count {
+            std::fs::remove_file(backup_path(n))?;
+            n -= 1;
+        }
+        for n in (1..count).rev() {
+            if backup_path(n).exists() {
+                std::fs::rename(backup_path(n), backup_path(n + 1))?;
+            }
+        }
+        write_atomic(&backup_path(1), &std::fs::read(dbpath)?)?;
+        Ok(())
     }
 
-    fn redo(&mut self) {
-        if self.current_snapshot == self.snapshots.len() { return };
-        let mut snapshot = State::create(self);
-        std::mem::swap(&mut snapshot, &mut self.snapshots[self.current_snapshot]);
-        snapshot.restore(self);
-        self.current_snapshot += 1;
-        self.needs_saving = true;
+    /// Path of the file holding undo history between sessions.
+    fn history_path(&self) -> String {
+        format!("{}.undo", self.config.dbpath)
     }
 
-    fn quit(&mut self) -> anyhow::Result<()> {
-        self.save()?;
-        self.quit = true;
-        Ok(())
+    /// Writes the undo history next to the database when quitting, tagged with a hash of the database it applies to.
+    /// This is best effort, as losing history should never prevent quitting.
+    fn save_history(&self) {
+        let Some(db_hash) = self.saved_hash.filter(|_| !self.config.read_only) else { return };
+        let saved_history = SavedHistory {
+            db_hash,
+            history: self.history.clone(),
+        };
+        if let Ok(history_str) = serde_yaml::to_string(&saved_history) {
+            let _ = write_atomic(Path::new(&self.history_path()), history_str.as_bytes());
+        }
     }
 
-    fn create_snapshot(&mut self) {
-        for i in (self.current_snapshot..self.snapshots.len()).rev() {
-            self.snapshots.remove(i);
-        }
-        self.snapshots.push_back(State::create(self));
-        self.current_snapshot += 1;
-        if self.snapshots.len() > self.max_snapshots {
-            self.snapshots.pop_front();
-            self.current_snapshot -= 1;
+    /// Loads the undo history of a previous session, for the database with the hash given.
+    /// History that is missing, corrupt, or was saved for a different database is ignored.
+    fn load_history(&mut self, db_hash: u64) {
+        let Ok(history_str) = std::fs::read_to_string(self.history_path()) else { return };
+        let Ok(saved_history) = serde_yaml::from_str::<SavedHistory>(&history_str) else { return };
+        if saved_history.db_hash != db_hash {
+            return;
         }
+        self.history = saved_history.history;
+        self.history.set_max_snapshots(self.config.max_snapshots); // The history may have been saved with another.
     }
-}
 
-/// Current item being selected in the [`App`].
+    fn ui_state_path(&self) -> String {
+        format!("{}.ui-state", self.config.dbpath)
+    }
+
+    /// Writes where the user left off next to the database, to reopen there. This is best effort, like the history.
+    /// Read-only sessions leave it as it was, along with everything else.
+    fn save_ui_state(&self) {
+        if self.config.read_only {
+            return;
+        }
+        let ui_state = UiState {
+            todo_list: self.selection.todo_list,
+            todo: self.selection.todo,
+            scroll_offsets: self.scroll_offsets.clone(),
+        };
+        if let Ok(ui_state_str) = serde_yaml::to_string(&ui_state) {
+            let _ = write_atomic(Path::new(&self.ui_state_path()), ui_state_str.as_bytes());
+        }
+    }
+
+    /// Returns to where the user left off, as far as the board still has it. Missing or unreadable files are ignored.
+    fn load_ui_state(&mut self) {
+        let Ok(ui_state_str) = std::fs::read_to_string(self.ui_state_path()) else { return };
+        let Ok(ui_state) = serde_yaml::from_str::<UiState>(&ui_state_str) else { return };
+        self.selection.todo_list = ui_state.todo_list;
+        self.selection.todo = ui_state.todo;
+        self.scroll_offsets = ui_state.scroll_offsets; // Kept in range when drawn.
+        self.clamp_selection();
+    }
+
+    fn undo(&mut self) {
+        let Some(snapshot) = self.history.undo(State::create(self)) else { return };
+        snapshot.restore(self);
+        self.clamp_selection();
+        self.needs_saving = true;
+    }
+
+    fn redo(&mut self) {
+        let Some(snapshot) = self.history.redo(State::create(self)) else { return };
+        snapshot.restore(self);
+        self.clamp_selection();
+        self.needs_saving = true;
+    }
+
+    /// Saves and quits, finishing a todo being edited first, as if leaving insert mode.
+    /// If saving fails, tdi stays open with nothing lost.
+    fn quit(&mut self) -> anyhow::Result<()> {
+        if self.mode == Mode::Insert {
+            self.set_mode(Mode::Normal);
+        }
+        self.save().context("Could not save")?;
+        self.save_history();
+        self.quit = true;
+        Ok(())
+    }
+
+    /// Saves right away when asked to, saying whether anything was written.
+    /// A todo being edited is saved as typed so far, without leaving insert mode.
+    fn save_now(&mut self) -> anyhow::Result<()> {
+        if !self.needs_saving {
+            self.post_message(MessageLevel::Info, "No changes");
+            return Ok(());
+        }
+        self.save().context("Could not save")?;
+        if self.message.is_none() {
+            self.post_message(MessageLevel::Info, "Saved");
+        }
+        Ok(())
+    }
+
+    /// Quits without saving, first asking to make sure if that would lose any changes.
+    fn confirm_force_quit(&mut self) {
+        if !self.needs_saving {
+            self.force_quit();
+            return;
+        }
+        self.confirm = Some(Confirm::ForceQuit);
+        self.mode = Mode::Confirm;
+    }
+
+    /// Quits without saving, leaving the database as it was last saved.
+    /// Text being typed is thrown away too, rather than offered back next time.
+    fn force_quit(&mut self) {
+        self.remove_insert_scratch();
+        self.quit = true;
+    }
+
+    /// Reloads the database from disk, first asking to make sure if that would replace any changes.
+    fn confirm_reload(&mut self) -> anyhow::Result<()> {
+        if !self.needs_saving {
+            return self.reload();
+        }
+        self.confirm = Some(Confirm::Reload);
+        self.mode = Mode::Confirm;
+        Ok(())
+    }
+
+    /// Replaces the board with the database as it is on disk, like after editing it elsewhere.
+    /// The reload is a step in the undo history like any other change, so undo returns to the board from before it,
+    /// unsaved changes included. If the database can't be read, the board is left as it was.
+    fn reload(&mut self) -> anyhow::Result<()> {
+        let dbpath = &self.config.dbpath;
+        let state = load_db(dbpath).with_context(|| format!("Could not reload {}", contract_tilde(dbpath)))?;
+        self.saved_hash = Some(state_hash(&state)).filter(|_| DbFormat::detect(dbpath).ok() == Some(self.db_format()));
+        self.create_snapshot();
+        self.todo_lists = state.todo_lists;
+        self.marks = state.marks;
+        self.assign_todo_ids();
+        self.clamp_selection();
+        self.needs_saving = false;
+        self.db_stamp = self.read_db_stamp();
+        self.post_message(MessageLevel::Info, format!("Reloaded {}", contract_tilde(&self.config.dbpath)));
+        Ok(())
+    }
+
+    /// Asks for a path to export the board to or import todos from, starting with the configured one.
+    fn open_prompt(&mut self, kind: PromptKind) {
+        let export_path = self.config.export_path.as_deref().unwrap_or("~/tdi.md");
+        self.prompt = Some(Prompt {
+            kind,
+            text: contract_tilde(&expand_tilde(export_path)),
+        });
+        self.mode = Mode::Prompt;
+    }
+
+    /// Acts on the path typed at the prompt. An empty path cancels.
+    fn submit_prompt(&mut self) -> anyhow::Result<()> {
+        self.mode = Mode::Normal;
+        let Some(prompt) = self.prompt.take() else { return Ok(()) };
+        if prompt.text.trim().is_empty() {
+            return Ok(());
+        }
+        let path = expand_tilde(prompt.text.trim());
+        match prompt.kind {
+            PromptKind::Export => self.confirm_export(path),
+            PromptKind::Import => self.import_markdown(&path),
+        }
+    }
+
+    /// Exports the board, first asking to make sure if that would overwrite an existing file.
+    fn confirm_export(&mut self, export_path: String) -> anyhow::Result<()> {
+        ExportFormat::for_path(&export_path)?;
+        if !Path::new(&export_path).exists() {
+            return self.export_board(&export_path);
+        }
+        self.confirm = Some(Confirm::OverwriteExport(export_path));
+        self.mode = Mode::Confirm;
+        Ok(())
+    }
+
+    /// Writes the board to a path in the format its extension names, replacing whatever is there.
+    fn export_board(&mut self, export_path: &str) -> anyhow::Result<()> {
+        let format = ExportFormat::for_path(export_path)?;
+        write_atomic(Path::new(export_path), format.export(&self.todo_lists).as_bytes())?;
+        self.post_message(MessageLevel::Info, format!("Exported to {}", contract_tilde(export_path)));
+        Ok(())
+    }
+
+    /// Adds the task items of a Markdown file to the board, like one exported and edited elsewhere.
+    /// Items before any heading go to the selected list. The whole import is a single step in the undo history.
+    fn import_markdown(&mut self, markdown_path: &str) -> anyhow::Result<()> {
+        if ExportFormat::for_path(markdown_path).ok() != Some(ExportFormat::Markdown) {
+            bail!("Can only import todos from Markdown, with a path ending in .md");
+        }
+        let markdown = std::fs::read_to_string(markdown_path)
+            .with_context(|| format!("Could not import {}", contract_tilde(markdown_path)))?;
+        self.create_snapshot();
+        let import = add_markdown_todos(&mut self.todo_lists, &markdown, self.selection.todo_list, self.config.import_duplicates);
+        if import.added == 0 {
+            self.discard_snapshot();
+        }
+        else {
+            self.assign_todo_ids();
+            self.needs_saving = true;
+        }
+        self.post_message(MessageLevel::Info, import.summary(&contract_tilde(markdown_path)));
+        Ok(())
+    }
+
+    /// Saves and quits when a signal asked tdi to terminate.
+    /// Quits even if saving fails, since whatever sent the signal won't wait, like a terminal being closed.
+    fn terminate(&mut self) -> anyhow::Result<()> {
+        let result = self.quit();
+        self.quit = true;
+        result
+    }
+
+    /// Discards the snapshot just created, for changes that turned out to be no-ops.
+    fn discard_snapshot(&mut self) {
+        self.history.discard_last();
+    }
+
+    fn create_snapshot(&mut self) {
+        self.history.record(State::create(self));
+    }
+}
+
+/// Overrides of where an [`App`] finds its files, from the command line.
+#[derive(Clone, Eq, PartialEq, Default, Debug)]
+pub struct Options {
+    pub dbpath: Option<String>,         // Database to edit instead of the config's.
+    pub config_path: Option<String>,    // Config file to use instead of the one in the config directory.
+    pub import_path: Option<String>,    // Database to copy into a new one, instead of starting.
+    pub export_path: Option<String>,    // File to export the board to, instead of starting.
+    pub read_only: bool,                // Views the database without ever changing it.
+    pub profile: Option<String>,        // Profile whose config and database to use instead of the usual ones.
+}
+
+/// Current item being selected in the [`App`].
 #[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
-struct Selection {
-    todo_list: usize, // Todo list selected
-    todo: usize,      // Todo in todo list selected
-    char: usize,      // Index of character in todo selected, if any
+pub(crate) struct Selection {
+    pub todo_list: usize, // Todo list selected
+    pub todo: usize,      // Todo in todo list selected
+    pub char: usize,      // Index of grapheme cluster in todo selected, if any
+    pub anchor: usize,    // Todo in todo list where the visual selection started
 }
 
 /// Configures an [App].
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 struct Config {
-    /// Todo-list dabase path.
+    /// Todo-list dabase path. A leading ~ stands for the home directory.
     dbpath: String,
+    /// Format the database is saved in, yaml, json or sqlite. Otherwise, json if dbpath ends in .json,
+    /// sqlite if it ends in .sqlite, and yaml if neither.
+    /// Databases are read in whichever format they are in, so changing this converts the database on the next start.
+    #[serde(default)]
+    db_format: Option<DbFormat>,
+    /// If true, selection follows a todo sent to another list. Otherwise, it stays put.
+    #[serde(default = "default_true")]
+    follow_sent_todo: bool,
+    /// If true, moving past the first or last todo or todo list wraps around to the other end.
+    #[serde(default)]
+    wrap_navigation: bool,
+    /// If true, long todo names wrap onto several rows. Otherwise, they are cut short with an ellipsis.
+    #[serde(default)]
+    wrap_todos: bool,
+    /// Numbering of todos in the gutter of each list: off, absolute, or relative to the selection.
+    #[serde(default)]
+    line_numbers: LineNumbers,
+    /// If true, hints of what can be typed pop up when a key waiting for a register was pressed a moment ago.
+    #[serde(default = "default_true")]
+    which_key: bool,
+    /// If true, deleting todos asks for confirmation first. Marked todos moved to the backlog never ask.
+    #[serde(default = "default_true")]
+    confirm_delete: bool,
+    /// What todos are prefixed with: a bullet, or a checkbox checked when marked.
+    #[serde(default)]
+    todo_style: TodoStyle,
+    /// If set, replaces the glyph todos are prefixed with, like "-" or "" for none.
+    #[serde(default)]
+    bullet: Option<String>,
+    /// If set, replaces the glyph marked todos are prefixed with. Otherwise, it is the same as `bullet`, if set.
+    #[serde(default)]
+    marked_bullet: Option<String>,
+    /// Preset theme, dark, light or high-contrast, or colors by role as names like "red" or hex strings like "#ff8800".
+    /// Colors override those of the preset named by a `preset` role, if any.
+    /// A `marked_style` role sets how marked todos are drawn, like "crossed_out dim" or "none".
+    #[serde(default)]
+    theme: serde_yaml::Value,
+    /// If set, the status bar shows the date and time in this strftime format, like "%a %d %b %H:%M".
+    #[serde(default)]
+    clock_format: Option<String>,
+    /// If true, the first run starts with sample todos explaining the basics. Otherwise, with empty lists.
+    #[serde(default = "default_true")]
+    sample_todos: bool,
+    /// If true, the status bar shows the keys for the most common actions of the current mode.
+    #[serde(default = "default_true")]
+    key_hints: bool,
+    /// If true, a strip above the status bar shows the full name of the selected todo, over up to two rows.
+    #[serde(default)]
+    preview: bool,
+    /// If true, clicking selects todos. Turning this off leaves the mouse to the terminal, to select text.
+    #[serde(default = "default_true")]
+    mouse: bool,
+    /// What turning the mouse wheel over a list does: select, moving the selection, or scroll, moving the view.
+    #[serde(default)]
+    wheel: WheelAction,
+    /// Number of rows or todos each notch of the mouse wheel moves by.
+    #[serde(default = "default_wheel_rows")]
+    wheel_rows: usize,
+    /// Milliseconds between ticks, which update things that change with time, like the clock.
+    #[serde(default = "default_tick_rate_ms")]
+    tick_rate_ms: u64,
+    /// Number of previous versions of the database kept when saving, like db.yml.1 for the latest. 0 keeps none.
+    #[serde(default = "default_backup_count")]
+    backup_count: usize,
+    /// Number of changes undo can go back, from 1 to 10000. Each is a copy of the whole board, kept in memory and
+    /// saved with the history, so big boards may want fewer.
+    #[serde(default = "default_max_snapshots")]
+    max_snapshots: usize,
+    /// Directory backups are kept in. Otherwise, they are kept next to the database.
+    #[serde(default)]
+    backup_dir: Option<String>,
+    /// When changes are saved without quitting: off, immediate, or `debounce: <seconds>` after the last key press.
+    /// Todos being edited are saved once leaving insert mode.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    autosave: Autosave,
+    /// If true, the database is reloaded when something else changes it, like a sync tool, checked every
+    /// couple of seconds in normal mode.
+    /// If there are unsaved changes, tdi asks whether to reload or keep them.
+    #[serde(default = "default_true")]
+    auto_reload: bool,
+    /// Path the prompts of E, exporting the board, and ctrl+e, importing todos, start with. Otherwise, ~/tdi.md.
+    /// A leading ~ stands for the home directory.
+    #[serde(default)]
+    export_path: Option<String>,
+    /// If true, todos imported from Markdown are added even if their list already has a todo of the same name.
+    /// Otherwise, they are skipped.
+    #[serde(default)]
+    import_duplicates: bool,
+    /// If true, and the database is in a git work tree, each save commits it, like "tdi: autosave 2024-01-31 12:00:00".
+    /// git runs in the background, and what it says when it fails is shown in the bottom row.
+    #[serde(default)]
+    git_autocommit: bool,
+    /// If true, the board can be viewed, searched and exported, but not changed, and the database is never written.
+    #[serde(default)]
+    read_only: bool,
+    /// If true, a row at the top shows the version of tdi and the path of the database being edited.
+    #[serde(default)]
+    header: bool,
+}
+
+fn default_true() -> bool { true }
+
+fn default_wheel_rows() -> usize { 3 }
+
+fn default_tick_rate_ms() -> u64 { 1000 }
+
+fn default_backup_count() -> usize { 3 }
+
+fn default_max_snapshots() -> usize { 100 }
+
+impl Config {
+    /// Refuses values that parse but can't be used, saying why.
+    fn validate(&self) -> anyhow::Result<()> {
+        if !(1..=MAX_SNAPSHOTS_LIMIT).contains(&self.max_snapshots) {
+            bail!(
+                "max_snapshots is {}, but must be from 1 to {MAX_SNAPSHOTS_LIMIT}. Each snapshot is a copy of the whole \
+                board, kept in memory and saved with the undo history, so more of them use more memory and disk",
+                self.max_snapshots,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Undo history, saved next to the database so undo works across sessions.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+struct SavedHistory {
+    db_hash: u64,               // Hash of the database the history applies to, as YAML whatever its format.
+    history: History<State>,
+}
+
+/// Where the user left off, saved apart from the database since it only matters on this machine.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+struct UiState {
+    #[serde(default)]
+    todo_list: usize,           // Todo list selected
+    #[serde(default)]
+    todo: usize,                // Todo in todo list selected
+    #[serde(default)]
+    scroll_offsets: Vec<usize>, // Index of the first row shown in each todo list.
+}
+
+/// Insert buffer of a todo being edited, saved to a scratch file while typing.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+struct InsertScratch {
+    todo_list: usize,
+    todo: usize,
+    original: String,   // Name of the todo before editing began.
+    name: String,       // Name of the todo as typed so far.
+    char: usize,
+}
+
+/// When changes are saved without quitting.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Autosave {
+    /// Only when quitting.
+    #[default]
+    Off,
+    /// After every key press that changed something.
+    Immediate,
+    /// Once no key has been pressed for some number of seconds.
+    Debounce(u64),
+}
+
+/// What turning the mouse wheel over a todo list does.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+enum WheelAction {
+    /// Selects the list and moves its selection.
+    #[default]
+    Select,
+    /// Scrolls the list, dragging the selection along only when it would leave the view.
+    Scroll,
+}
+
+/// Direction the mouse wheel was turned in. Sideways includes turning it while holding shift.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum WheelDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Order to sort a todo list by.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum SortOrder {
+    /// By name, ignoring case.
+    Alphabetical,
+    /// Unmarked todos first, otherwise keeping the current order.
+    MarkedLast,
+}
+
+impl SortOrder {
+    fn next(self) -> Self {
+        match self {
+            Self::Alphabetical => Self::MarkedLast,
+            Self::MarkedLast => Self::Alphabetical,
+        }
+    }
+}
+
+/// Question awaiting a yes/no answer from the user.
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Confirm {
+    /// Restore text typed before tdi exited mid-insert.
+    RestoreInsert(InsertScratch),
+    /// Delete every marked todo in a todo list.
+    DeleteMarked {
+        todo_list: usize,
+        todo_list_name: String,
+        count: usize,
+    },
+    /// Delete a range of todos in a todo list, of which `count` are unmarked and would be deleted outright.
+    DeleteTodos {
+        todo_list: usize,
+        todos: Range<usize>,
+        first_name: String,
+        count: usize,
+    },
+    /// Quit, losing changes made since the database was last saved.
+    ForceQuit,
+    /// Reload the database, replacing changes made since it was last saved.
+    Reload,
+    /// Reload the database, which changed on disk, replacing changes made since it was last saved here.
+    ChangedOnDisk,
+    /// Export the board to a file that already exists, replacing it.
+    OverwriteExport(String),
+}
+
+impl Confirm {
+    fn prompt(&self) -> String {
+        match self {
+            Self::RestoreInsert(scratch) => format!("Restore unsaved edit '{}'? (y/n)", scratch.name),
+            Self::DeleteMarked { todo_list_name, count, .. } => {
+                format!("Delete {count} marked todo(s) from {todo_list_name}? (y/n)")
+            }
+            Self::DeleteTodos { first_name, count: 1, .. } => format!("Delete '{first_name}'? (y/n)"),
+            Self::DeleteTodos { count, .. } => format!("Delete {count} todos? (y/n)"),
+            Self::ForceQuit => "Quit without saving? Changes since the last save will be lost (y/n)".to_string(),
+            Self::Reload => "Reload from disk, replacing unsaved changes? u undoes the reload (y/n)".to_string(),
+            Self::ChangedOnDisk => "Database changed on disk. Reload it, replacing your unsaved changes? n keeps yours (y/n)".to_string(),
+            Self::OverwriteExport(export_path) => format!("{} already exists. Overwrite it? (y/n)", contract_tilde(export_path)),
+        }
+    }
+}
+
+/// Line of text typed in the bottom row, in prompt mode.
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Prompt {
+    kind: PromptKind,
+    text: String,
+}
+
+/// What the text typed at a [`Prompt`] is for.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum PromptKind {
+    /// Path to export the board to, in the format its extension names.
+    Export,
+    /// Path of a Markdown file to import todos from.
+    Import,
+}
+
+impl Prompt {
+    /// Draws the prompt with the end of the text typed, and the cursor after it.
+    fn render(&self, area: Rect, frame: &mut Frame) {
+        let label = match self.kind {
+            PromptKind::Export => "Export to: ",
+            PromptKind::Import => "Import from: ",
+        };
+        let width = (area.width as usize).saturating_sub(label.width());
+        let start = scroll_to_cursor(&self.text, grapheme_count(&self.text), width);
+        let shown = &self.text[byte_index(&self.text, start)..];
+        frame.render_widget(Line::from(format!("{label}{shown}")), area);
+        let cursor_x = area.x + (label.width() + shown.width()) as u16;
+        frame.set_cursor_position((cursor_x.min(area.right().saturating_sub(1)), area.y));
+    }
+}
+
+/// Transient message shown in the bottom row.
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Message {
+    level: MessageLevel,
+    text: String,
+    posted_at: Instant,
+}
+
+/// How serious a [`Message`] is, setting its color.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum MessageLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl MessageLevel {
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            Self::Info => theme.fg_unselected,
+            Self::Warning => theme.fg_warning,
+            Self::Error => theme.fg_error,
+        }
+    }
 }
 
 /// Subset of the fields in [`App`], which are saved to a database file.
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
-struct State {
-    version: String,
-    todo_lists: Vec<TodoList>,
+pub(crate) struct State {
+    pub version: String,
+    pub todo_lists: Vec<TodoList>,
+    #[serde(default)]
+    pub marks: BTreeMap<char, u64>,
+    #[serde(skip)]
+    selection: Selection,   // Only used by snapshots, so undo and redo return to where the change was made.
 }
 
 impl State {
     fn create(app: &App) -> Self {
         Self {
             todo_lists: app.todo_lists.clone(),
+            marks: app.marks.clone(),
+            selection: app.selection,
             ..Default::default()
         }
     }
 
     fn restore(self, app: &mut App) {
         app.todo_lists = self.todo_lists;
+        app.marks = self.marks;
+        app.selection = self.selection;
+    }
+}
+
+/// Sample todos filling the Todo list on first run, each explaining a key binding.
+const SAMPLE_TODOS: &[&str] = &[
+    "Press i to edit me, then Esc when done",
+    "Press o to add a todo below, O above",
+    "Press m to mark me done, then d to send me to the Backlog",
+    "Press H and L to move me between lists",
+    "Press ? for help",
+];
+
+impl State {
+    /// Board shown on first run, with sample todos in the Todo list.
+    fn welcome() -> Self {
+        let mut state = Self::default();
+        state.todo_lists[0].todos = SAMPLE_TODOS.iter().map(|&name| Todo::new(name)).collect();
+        state
     }
 }
 
@@ -595,6 +2667,8 @@ impl Default for State {
                     todos: vec![],
                 },
             ],
+            marks: BTreeMap::new(),
+            selection: Selection::default(),
         }
     }
 }
@@ -603,10 +2677,19 @@ impl Default for State {
 fn default_key_mappings() -> HashMap<KeyPress, Action> {
     let mut res = HashMap::new();
     res.insert(KeyPress::char(Mode::Normal, 'q'),                                       Action::Quit);
+    for mode in Mode::ALL {
+        res.insert(KeyPress::new(mode, KeyCode::Char('c'), KeyModifiers::CONTROL),      Action::Quit);
+    }
+    for mode in [Mode::Normal, Mode::Visual, Mode::Insert] {
+        res.insert(KeyPress::new(mode, KeyCode::Char('s'), KeyModifiers::CONTROL),      Action::Save);
+        res.insert(KeyPress::new(mode, KeyCode::Char('q'), KeyModifiers::CONTROL),      Action::ForceQuit);
+    }
     res.insert(KeyPress::char(Mode::Normal, 'o'),                                       Action::AddTodoBelow);
     res.insert(KeyPress::char(Mode::Normal, 'O'),                                       Action::AddTodoAbove);
     res.insert(KeyPress::char(Mode::Normal, 'm'),                                       Action::ToggleMark);
     res.insert(KeyPress::char(Mode::Normal, 'd'),                                       Action::DeleteTodo);
+    res.insert(KeyPress::char(Mode::Normal, 'D'),                                       Action::DeleteMarked);
+    res.insert(KeyPress::char(Mode::Normal, '='),                                       Action::SortList);
     res.insert(KeyPress::char(Mode::Normal, 'H'),                                       Action::MoveTodoLeft);
     res.insert(KeyPress::char(Mode::Normal, 'J'),                                       Action::MoveTodoDown);
     res.insert(KeyPress::char(Mode::Normal, 'K'),                                       Action::MoveTodoUp);
@@ -615,6 +2698,8 @@ fn default_key_mappings() -> HashMap<KeyPress, Action> {
     res.insert(KeyPress::new(Mode::Normal, KeyCode::Down, KeyModifiers::SHIFT),         Action::MoveTodoDown);
     res.insert(KeyPress::new(Mode::Normal, KeyCode::Up, KeyModifiers::SHIFT),           Action::MoveTodoUp);
     res.insert(KeyPress::new(Mode::Normal, KeyCode::Right, KeyModifiers::SHIFT),        Action::MoveTodoRight);
+    res.insert(KeyPress::new(Mode::Normal, KeyCode::Up, KeyModifiers::CONTROL | KeyModifiers::SHIFT),   Action::MoveTodoTop);
+    res.insert(KeyPress::new(Mode::Normal, KeyCode::Down, KeyModifiers::CONTROL | KeyModifiers::SHIFT), Action::MoveTodoBottom);
     res.insert(KeyPress::char(Mode::Normal, 'K'),                                       Action::MoveTodoUp);
     res.insert(KeyPress::char(Mode::Normal, 'L'),                                       Action::MoveTodoRight);
     res.insert(KeyPress::char(Mode::Normal, 'h'),                                       Action::MoveLeft);
@@ -622,6 +2707,8 @@ fn default_key_mappings() -> HashMap<KeyPress, Action> {
     res.insert(KeyPress::char(Mode::Normal, 'k'),                                       Action::MoveUp);
     res.insert(KeyPress::new(Mode::Normal, KeyCode::Char('d'), KeyModifiers::CONTROL),  Action::MoveDownHalf);
     res.insert(KeyPress::new(Mode::Normal, KeyCode::Char('u'), KeyModifiers::CONTROL),  Action::MoveUpHalf);
+    res.insert(KeyPress::code(Mode::Normal, KeyCode::PageUp),                           Action::MovePageUp);
+    res.insert(KeyPress::code(Mode::Normal, KeyCode::PageDown),                         Action::MovePageDown);
     res.insert(KeyPress::char(Mode::Normal, 'k'),                                       Action::MoveUp);
     res.insert(KeyPress::char(Mode::Normal, 'l'),                                       Action::MoveRight);
     res.insert(KeyPress::char(Mode::Normal, 'g'),                                       Action::MoveTop);
@@ -632,68 +2719,549 @@ fn default_key_mappings() -> HashMap<KeyPress, Action> {
     res.insert(KeyPress::code(Mode::Normal, KeyCode::Down),                             Action::MoveDown);
     res.insert(KeyPress::code(Mode::Normal, KeyCode::Up),                               Action::MoveUp);
     res.insert(KeyPress::code(Mode::Normal, KeyCode::Right),                            Action::MoveRight);
+    res.insert(KeyPress::char(Mode::Normal, 'Q'),                                       Action::RecordMacro);
+    res.insert(KeyPress::char(Mode::Normal, '@'),                                       Action::AwaitRegister(RegisterAction::ReplayMacro));
+    for digit in 0..10 {
+        let c = char::from_digit(digit as u32, 10).unwrap();
+        res.insert(KeyPress::char(Mode::Normal, c),                                     Action::Count(digit));
+    }
+    res.insert(KeyPress::char(Mode::Normal, 'M'),                                       Action::AwaitRegister(RegisterAction::SetMark));
+    res.insert(KeyPress::char(Mode::Normal, '\''),                                      Action::AwaitRegister(RegisterAction::JumpToMark));
     res.insert(KeyPress::char(Mode::Normal, 'u'),                                       Action::Undo);
+    res.insert(KeyPress::new(Mode::Normal, KeyCode::Char('r'), KeyModifiers::CONTROL),  Action::Reload);
+    res.insert(KeyPress::char(Mode::Normal, 'E'),                                       Action::Export);
+    res.insert(KeyPress::new(Mode::Normal, KeyCode::Char('e'), KeyModifiers::CONTROL),  Action::Import);
     res.insert(KeyPress::char(Mode::Normal, 'r'),                                       Action::Redo);
     res.insert(KeyPress::char(Mode::Normal, 'i'),                                       Action::SetMode(Mode::Insert));
+    res.insert(KeyPress::char(Mode::Normal, 'S'),                                       Action::ChangeTodo);
+    res.insert(KeyPress::char(Mode::Normal, 'a'),                                       Action::Append);
+    res.insert(KeyPress::char(Mode::Normal, 'A'),                                       Action::SetMode(Mode::Insert));
+    res.insert(KeyPress::char(Mode::Normal, 'I'),                                       Action::InsertStart);
+    res.insert(KeyPress::char(Mode::Normal, 'p'),                                       Action::PasteClipboard);
+    res.insert(KeyPress::char(Mode::Normal, 'Y'),                                       Action::YankToClipboard);
+    res.insert(KeyPress::char(Mode::Normal, '#'),                                       Action::ToggleLineNumbers);
+    res.insert(KeyPress::char(Mode::Normal, 'P'),                                       Action::TogglePreview);
+    res.insert(KeyPress::char(Mode::Normal, '?'),                                       Action::ShowHelp);
+    res.insert(KeyPress::code(Mode::Normal, KeyCode::Enter),                            Action::ShowDetail);
     res.insert(KeyPress::code(Mode::Insert, KeyCode::Esc),                              Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::code(Mode::Insert, KeyCode::Enter),                            Action::InsertNext);
     res.insert(KeyPress::code(Mode::Insert, KeyCode::Right),                            Action::MoveCursorRight);
     res.insert(KeyPress::code(Mode::Insert, KeyCode::Left),                             Action::MoveCursorLeft);
     res.insert(KeyPress::code(Mode::Insert, KeyCode::Home),                             Action::MoveCursorStart);
     res.insert(KeyPress::code(Mode::Insert, KeyCode::End),                              Action::MoveCursorEnd);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Right, KeyModifiers::CONTROL),      Action::MoveCursorWordForward);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Left, KeyModifiers::CONTROL),       Action::MoveCursorWordBackward);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Char('f'), KeyModifiers::ALT),      Action::MoveCursorWordForward);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Char('b'), KeyModifiers::ALT),      Action::MoveCursorWordBackward);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Char('w'), KeyModifiers::CONTROL),  Action::DeleteWordBackward);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Char('u'), KeyModifiers::CONTROL),  Action::DeleteToStart);
+    res.insert(KeyPress::code(Mode::Insert, KeyCode::Tab),                              Action::MoveEditingTodoRight);
+    res.insert(KeyPress::code(Mode::Insert, KeyCode::BackTab),                          Action::MoveEditingTodoLeft);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::BackTab, KeyModifiers::SHIFT),      Action::MoveEditingTodoLeft);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Char('k'), KeyModifiers::CONTROL),  Action::DeleteToEnd);
+    res.insert(KeyPress::new(Mode::Insert, KeyCode::Char('v'), KeyModifiers::CONTROL),  Action::PasteClipboard);
+    res.insert(KeyPress::char(Mode::Normal, 'v'),                                       Action::SetMode(Mode::Visual));
+    res.insert(KeyPress::code(Mode::Visual, KeyCode::Esc),                              Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::char(Mode::Visual, 'v'),                                       Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::char(Mode::Visual, 'j'),                                       Action::MoveDown);
+    res.insert(KeyPress::char(Mode::Visual, 'k'),                                       Action::MoveUp);
+    res.insert(KeyPress::code(Mode::Visual, KeyCode::Down),                             Action::MoveDown);
+    res.insert(KeyPress::code(Mode::Visual, KeyCode::Up),                               Action::MoveUp);
+    res.insert(KeyPress::new(Mode::Visual, KeyCode::Char('d'), KeyModifiers::CONTROL),  Action::MoveDownHalf);
+    res.insert(KeyPress::new(Mode::Visual, KeyCode::Char('u'), KeyModifiers::CONTROL),  Action::MoveUpHalf);
+    res.insert(KeyPress::code(Mode::Visual, KeyCode::PageUp),                           Action::MovePageUp);
+    res.insert(KeyPress::code(Mode::Visual, KeyCode::PageDown),                         Action::MovePageDown);
+    res.insert(KeyPress::char(Mode::Visual, 'g'),                                       Action::MoveTop);
+    res.insert(KeyPress::char(Mode::Visual, 'G'),                                       Action::MoveBottom);
+    res.insert(KeyPress::char(Mode::Visual, 'd'),                                       Action::DeleteTodo);
+    res.insert(KeyPress::char(Mode::Visual, 'm'),                                       Action::ToggleMark);
+    res.insert(KeyPress::char(Mode::Visual, 'y'),                                       Action::YankToClipboard);
+    res.insert(KeyPress::char(Mode::Visual, 'Y'),                                       Action::YankToClipboard);
+    res.insert(KeyPress::char(Mode::Visual, 'H'),                                       Action::MoveTodoLeft);
+    res.insert(KeyPress::char(Mode::Visual, 'L'),                                       Action::MoveTodoRight);
+    res.insert(KeyPress::char(Mode::Normal, 's'),                                       Action::SendTodoTo);
+    res.insert(KeyPress::code(Mode::Picker, KeyCode::Esc),                              Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::code(Mode::Picker, KeyCode::Enter),                            Action::PickerConfirm);
+    res.insert(KeyPress::char(Mode::Picker, 'j'),                                       Action::PickerNext);
+    res.insert(KeyPress::char(Mode::Picker, 'k'),                                       Action::PickerPrev);
+    res.insert(KeyPress::code(Mode::Picker, KeyCode::Down),                             Action::PickerNext);
+    res.insert(KeyPress::code(Mode::Picker, KeyCode::Up),                               Action::PickerPrev);
+    for (i, c) in ('1'..='9').enumerate() {
+        res.insert(KeyPress::char(Mode::Picker, c),                                     Action::PickerChoose(i));
+    }
+    res.insert(KeyPress::code(Mode::Popup, KeyCode::Esc),                               Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::code(Mode::Popup, KeyCode::Enter),                             Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::char(Mode::Popup, 'q'),                                        Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::char(Mode::Popup, '?'),                                        Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::char(Mode::Popup, 'j'),                                        Action::PopupScrollDown);
+    res.insert(KeyPress::char(Mode::Popup, 'k'),                                        Action::PopupScrollUp);
+    res.insert(KeyPress::code(Mode::Popup, KeyCode::Down),                              Action::PopupScrollDown);
+    res.insert(KeyPress::code(Mode::Popup, KeyCode::Up),                                Action::PopupScrollUp);
+    res.insert(KeyPress::char(Mode::Confirm, 'y'),                                      Action::ConfirmYes);
+    res.insert(KeyPress::char(Mode::Confirm, 'n'),                                      Action::ConfirmNo);
+    res.insert(KeyPress::code(Mode::Confirm, KeyCode::Esc),                             Action::ConfirmNo);
+    res.insert(KeyPress::code(Mode::Prompt, KeyCode::Enter),                            Action::SubmitPrompt);
+    res.insert(KeyPress::code(Mode::Prompt, KeyCode::Esc),                              Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::new(Mode::Normal, KeyCode::Char('p'), KeyModifiers::CONTROL),  Action::SetMode(Mode::Finder));
+    res.insert(KeyPress::code(Mode::Finder, KeyCode::Esc),                              Action::SetMode(Mode::Normal));
+    res.insert(KeyPress::code(Mode::Finder, KeyCode::Enter),                            Action::FinderJump);
+    res.insert(KeyPress::code(Mode::Finder, KeyCode::Down),                             Action::FinderNext);
+    res.insert(KeyPress::code(Mode::Finder, KeyCode::Up),                               Action::FinderPrev);
+    res.insert(KeyPress::new(Mode::Finder, KeyCode::Char('n'), KeyModifiers::CONTROL),  Action::FinderNext);
+    res.insert(KeyPress::new(Mode::Finder, KeyCode::Char('p'), KeyModifiers::CONTROL),  Action::FinderPrev);
     res
 }
 
-fn load_app_config() -> anyhow::Result<Config> {
-    let home_dir = std::env::var("HOME")?;
-    let config_dir = format!("{home_dir}/.config/tdi");
-    std::fs::create_dir_all(&config_dir)?;
-    let config_path = format!("{config_dir}/config.yml");
+/// Turns off the terminal modes turned on while running, if they were.
+fn leave_terminal_modes(mouse: bool, keyboard_enhanced: bool) -> std::io::Result<()> {
+    if mouse {
+        crossterm::execute!(std::io::stdout(), DisableMouseCapture)?;
+    }
+    if keyboard_enhanced {
+        crossterm::execute!(std::io::stdout(), PopKeyboardEnhancementFlags)?;
+    }
+    Ok(())
+}
+
+/// Loads the config, with paths overridden by the options given or the environment.
+fn load_config(options: &Options) -> anyhow::Result<Config> {
+    let paths = Paths::resolve(options, |name| std::env::var(name).ok());
+    let mut config = load_app_config(paths.config.as_deref(), paths.profile.as_deref())?;
+    if let Some(dbpath) = paths.db {
+        config.dbpath = expand_tilde(&dbpath);
+    }
+    config.read_only |= options.read_only;
+    Ok(config)
+}
+
+/// Loads the config from the path given, or from the config directory, or the profile's directory inside it if there
+/// is a profile. Only the config directory's may be missing, in which case the default config is used, with the
+/// database in the data directory, or the profile's directory inside that. Config files ending in .toml are TOML, and others YAML.
+/// The config directory's config.toml is used over its config.yml if both exist.
+fn load_app_config(config_path: Option<&str>, profile: Option<&str>) -> anyhow::Result<Config> {
+    let config_path = match config_path {
+        Some(config_path) => {
+            let config_path = PathBuf::from(expand_tilde(config_path));
+            if !std::fs::exists(&config_path)? {
+                bail!("Config file {} does not exist", config_path.display());
+            }
+            config_path
+        }
+        None => {
+            let config_dir = profile_dir(config_dir()?, profile)?;
+            std::fs::create_dir_all(&config_dir)?;
+            config_file_in(&config_dir)
+        }
+    };
     if !std::fs::exists(&config_path)? {
-        Ok(Config {
-            dbpath: format!("{home_dir}/.local/share/tdi/db.yml"),
-        })
+        // Every setting but dbpath has a default, so this is the default config.
+        let mut config: Config = serde_yaml::from_str("dbpath: ''")?;
+        config.dbpath = profile_dir(data_dir()?, profile)?.join("db.yml").to_string_lossy().into_owned();
+        Ok(config)
     } else {
-        let config_str: String = std::fs::read_to_string(config_path)?;
-        let config: Config = serde_yaml::from_str(&config_str)?;
+        let config_str: String = std::fs::read_to_string(&config_path)?;
+        let is_toml = config_path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+        let config: anyhow::Result<Config> = match is_toml {
+            true => toml::from_str(&config_str).map_err(Into::into),
+            false => serde_yaml::from_str(&config_str).map_err(Into::into),
+        };
+        let config = config.and_then(|config| config.validate().map(|()| config));
+        let mut config = config.with_context(|| format!("Invalid config file {}", config_path.display()))?;
+        config.dbpath = expand_tilde(&config.dbpath);
         Ok(config)
     }
 }
 
-fn load_app_state(dbpath: &str) -> anyhow::Result<State> {
-    let state_string = std::fs::read_to_string(dbpath)?;
-    let state = serde_yaml::from_str(&state_string)?;
-    Ok(state)
+/// Config file in a config directory: config.toml if there is one, and config.yml otherwise, even if missing.
+fn config_file_in(config_dir: &Path) -> PathBuf {
+    match config_dir.join("config.toml") {
+        toml_path if toml_path.exists() => toml_path,
+        _ => config_dir.join("config.yml"),
+    }
+}
+
+/// Changelog lines for every version newer than the one the database was last saved with.
+/// None for fresh databases, and for databases saved by the same or a newer version of tdi.
+fn whats_new(last_version: &str) -> Option<Vec<String>> {
+    let last_version = parse_version(last_version);
+    let app_version = parse_version(APP_VERSION);
+    if last_version >= app_version {
+        return None;
+    }
+    let mut lines = vec![];
+    for (version, entries) in CHANGELOG {
+        let version_parsed = parse_version(version);
+        if version_parsed <= last_version || version_parsed > app_version {
+            continue;
+        }
+        lines.push(format!("{version}:"));
+        lines.extend(entries.iter().map(|entry| format!("• {entry}")));
+    }
+    match lines.is_empty() {
+        true => None,
+        false => Some(lines),
+    }
+}
+
+/// Parses a dotted version like "0.2" for comparison. Unparsable components count as 0.
+pub(crate) fn parse_version(version: &str) -> Vec<u32> {
+    let mut parts: Vec<u32> = version
+        .split('.')
+        .map(|part| part.trim().parse().unwrap_or(0))
+        .collect();
+    while parts.last() == Some(&0) {
+        parts.pop();
+    }
+    parts
+}
+
+/// FNV-1a hash of some text, which unlike the std hasher is stable across Rust versions.
+fn content_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Hash of a database's contents, as YAML whatever format it is kept in, so it is the same for every format.
+fn state_hash(state: &State) -> u64 {
+    content_hash(&serde_yaml::to_string(state).unwrap_or_default())
+}
+
+/// Loads the database, in whichever format it is in. One that can be read but not parsed, like after a bad hand edit,
+/// is moved aside so it isn't lost, and an empty board is returned in its place along with what happened.
+/// One saved by a newer version of tdi is an error, and left alone. Returns the format it was in too.
+fn load_app_state(dbpath: &str) -> anyhow::Result<(State, DbFormat, Option<CorruptDb>)> {
+    let format = DbFormat::detect(dbpath).with_context(|| format!("Could not open {dbpath}"))?;
+    let err = match format.storage(dbpath).load() {
+        Ok(state) => return Ok((state, format, None)),
+        Err(LoadError::Unreadable(err)) => return Err(err.context(format!("Could not open {dbpath}"))),
+        Err(LoadError::Corrupt(err)) => err,
+    };
+    let moved_to = move_aside_corrupt_db(dbpath).with_context(|| format!("Invalid database {dbpath}: {err:#}"))?;
+    let corrupt_db = CorruptDb {
+        moved_to,
+        error: format!("{err:#}"),
+    };
+    Ok((State::default(), format, Some(corrupt_db)))
+}
+
+/// Loads the database at a path, in whichever format it is in, without trying to recover it if it's corrupt.
+fn load_db(path: &str) -> anyhow::Result<State> {
+    match DbFormat::detect(path)?.storage(path).load() {
+        Ok(state) => Ok(state),
+        Err(LoadError::Unreadable(err) | LoadError::Corrupt(err)) => Err(err),
+    }
+}
+
+/// Adds the task items of the Markdown file at `markdown_path` to the configured database, or a new one if there is
+/// none yet. Items before any heading go to the first list. Returns what was done, to print.
+fn import_markdown(config: &Config, markdown_path: &str) -> anyhow::Result<String> {
+    let markdown = std::fs::read_to_string(markdown_path).with_context(|| format!("Could not import {markdown_path}"))?;
+    let dbpath = &config.dbpath;
+    let mut state = match Path::new(dbpath).exists() {
+        true => load_db(dbpath).with_context(|| format!("Could not import into {dbpath}"))?,
+        false => State::default(),
+    };
+    let import = add_markdown_todos(&mut state.todo_lists, &markdown, 0, config.import_duplicates);
+    if let Some(parent) = Path::new(dbpath).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let db_format = config.db_format.unwrap_or_else(|| DbFormat::for_path(dbpath));
+    db_format.storage(dbpath).save(&state)?;
+    Ok(import.summary(&format!("{markdown_path} into {dbpath}")))
+}
+
+/// Renames a database that could not be parsed to one like db.yml.corrupt-20240131-120000,
+/// numbered if that is taken, so nothing saved later can overwrite it. Returns the new path.
+fn move_aside_corrupt_db(dbpath: &str) -> anyhow::Result<String> {
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S");
+    let mut moved_to = format!("{dbpath}.corrupt-{timestamp}");
+    let mut n = 1;
+    while Path::new(&moved_to).exists() {
+        n += 1;
+        moved_to = format!("{dbpath}.corrupt-{timestamp}-{n}");
+    }
+    std::fs::rename(dbpath, &moved_to)?;
+    Ok(moved_to)
+}
+
+/// Database that could not be parsed when starting, and was moved aside.
+struct CorruptDb {
+    moved_to: String,
+    error: String,
+}
+
+impl CorruptDb {
+    fn explanation(&self) -> Vec<String> {
+        vec![
+            "The database could not be read, so tdi started with an empty board.".to_string(),
+            String::new(),
+            "The original was kept, unchanged, at:".to_string(),
+            contract_tilde(&self.moved_to),
+            String::new(),
+            "The problem was:".to_string(),
+            self.error.clone(),
+            String::new(),
+            "To recover your todos, fix it and move it back while tdi isn't running.".to_string(),
+        ]
+    }
+}
+
+/// Has SIGTERM and SIGHUP set [`TERMINATE`] instead of killing tdi, so it can save and restore the terminal first.
+fn watch_terminate_signals() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGHUP] {
+        signal_hook::flag::register(signal, Arc::clone(&TERMINATE))?;
+    }
+    Ok(())
 }
 
 /// Value that causes an [`App`] to perform an action.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 enum Action {
     Quit,
+    Save,
+    ForceQuit,
+    Reload,
+    Export,
+    Import,
+    Terminate,
     DeleteTodo,
+    DeleteMarked,
+    SortList,
+    ToggleLineNumbers,
+    TogglePreview,
+    ShowHelp,
+    ShowDetail,
+    Click { column: u16, row: u16 },
+    Wheel { column: u16, row: u16, direction: WheelDirection },
+    ShowHints,
+    ExpireMessage,
+    Tick,
+    PopupScrollDown,
+    PopupScrollUp,
     MoveTodoLeft,
     MoveTodoRight,
     MoveTodoUp,
     MoveTodoDown,
+    MoveTodoTop,
+    MoveTodoBottom,
     MoveLeft,
     MoveRight,
     MoveUp,
     MoveDown,
     MoveUpHalf,
     MoveDownHalf,
+    MovePageUp,
+    MovePageDown,
     MoveTop,
     MoveBottom,
     AddTodoAbove,
     AddTodoBelow,
     ToggleMark,
     Input(KeyCode),
+    PasteClipboard,
+    YankToClipboard,
     SetMode(Mode),
+    InsertStart,
+    Append,
+    ChangeTodo,
+    InsertNext,
     MoveCursorRight,
     MoveCursorLeft,
     MoveCursorStart,
     MoveCursorEnd,
+    MoveCursorWordForward,
+    MoveCursorWordBackward,
+    DeleteWordBackward,
+    DeleteToStart,
+    DeleteToEnd,
+    MoveEditingTodoLeft,
+    MoveEditingTodoRight,
+    FinderNext,
+    FinderPrev,
+    FinderJump,
+    SendTodoTo,
+    PickerNext,
+    PickerPrev,
+    PickerConfirm,
+    PickerChoose(usize),
+    ConfirmYes,
+    ConfirmNo,
+    SubmitPrompt,
     Undo,
     Redo,
+    RecordMacro,
+    AwaitRegister(RegisterAction),
+    WithRegister(RegisterAction, char),
+    CancelRegister,
+    Count(usize),
     Nop, // No operation. Useful if app needs to rerender.
+    Autosave,
+}
+
+impl Action {
+    /// True if the action comes from a key press or click, rather than waiting for one timing out, a signal,
+    /// or the terminal resizing.
+    fn is_key_press(self) -> bool {
+        !matches!(self, Self::Terminate | Self::Autosave | Self::ShowHints | Self::ExpireMessage | Self::Tick | Self::Nop)
+    }
+
+    /// What the action does, as shown in the help popup.
+    fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::Save => "Save now",
+            Self::ForceQuit => "Quit without saving",
+            Self::Reload => "Reload the database from disk",
+            Self::Export => "Export the board as Markdown or CSV",
+            Self::Import => "Import todos from Markdown",
+            Self::Terminate => "Save and quit on a signal",
+            Self::DeleteTodo => "Delete todo, or send marked todo to backlog",
+            Self::DeleteMarked => "Delete all marked todos in list",
+            Self::SortList => "Sort list, again to change order",
+            Self::ToggleLineNumbers => "Cycle line numbers",
+            Self::TogglePreview => "Toggle preview of the selected todo",
+            Self::ShowHelp => "Show this help",
+            Self::ShowDetail => "Show todo in full",
+            Self::Click { .. } => "Select clicked todo",
+            Self::Wheel { .. } => "Scroll list under pointer",
+            Self::ShowHints => "Show register hints",
+            Self::ExpireMessage => "Clear message",
+            Self::Tick => "Update clock",
+            Self::PopupScrollDown => "Scroll down",
+            Self::PopupScrollUp => "Scroll up",
+            Self::MoveTodoLeft => "Move todo to list on the left",
+            Self::MoveTodoRight => "Move todo to list on the right",
+            Self::MoveTodoUp => "Move todo up",
+            Self::MoveTodoDown => "Move todo down",
+            Self::MoveTodoTop => "Move todo to top",
+            Self::MoveTodoBottom => "Move todo to bottom",
+            Self::MoveLeft => "Select list on the left",
+            Self::MoveRight => "Select list on the right",
+            Self::MoveUp => "Select todo above",
+            Self::MoveDown => "Select todo below",
+            Self::MoveUpHalf => "Move up half a page",
+            Self::MoveDownHalf => "Move down half a page",
+            Self::MovePageUp => "Move up a page",
+            Self::MovePageDown => "Move down a page",
+            Self::MoveTop => "Select first todo",
+            Self::MoveBottom => "Select last todo",
+            Self::AddTodoAbove => "Add todo above",
+            Self::AddTodoBelow => "Add todo below",
+            Self::ToggleMark => "Toggle mark",
+            Self::Input(_) => "Type text",
+            Self::PasteClipboard => "Paste from clipboard",
+            Self::YankToClipboard => "Copy to clipboard",
+            Self::SetMode(Mode::Normal) => "Back to normal mode",
+            Self::SetMode(Mode::Insert) => "Edit todo at the end",
+            Self::SetMode(Mode::Finder) => "Find todo",
+            Self::SetMode(Mode::Visual) => "Select a range of todos",
+            Self::SetMode(_) => "Change mode",
+            Self::InsertStart => "Edit todo at the start",
+            Self::Append => "Edit todo after the last edit",
+            Self::ChangeTodo => "Retype todo",
+            Self::InsertNext => "Start a new todo below",
+            Self::MoveCursorRight => "Cursor right",
+            Self::MoveCursorLeft => "Cursor left",
+            Self::MoveCursorStart => "Cursor to start",
+            Self::MoveCursorEnd => "Cursor to end",
+            Self::MoveCursorWordForward => "Cursor to next word",
+            Self::MoveCursorWordBackward => "Cursor to previous word",
+            Self::DeleteWordBackward => "Delete previous word",
+            Self::DeleteToStart => "Delete to start",
+            Self::DeleteToEnd => "Delete to end",
+            Self::MoveEditingTodoLeft => "Move todo to list on the left",
+            Self::MoveEditingTodoRight => "Move todo to list on the right",
+            Self::FinderNext => "Next match",
+            Self::FinderPrev => "Previous match",
+            Self::FinderJump => "Jump to match",
+            Self::SendTodoTo => "Send todo to another list",
+            Self::PickerNext => "Next list",
+            Self::PickerPrev => "Previous list",
+            Self::PickerConfirm => "Choose list",
+            Self::PickerChoose(_) => "Choose numbered list",
+            Self::ConfirmYes => "Yes",
+            Self::ConfirmNo => "No",
+            Self::SubmitPrompt => "Use the path typed",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::RecordMacro => "Record macro, again to stop",
+            Self::AwaitRegister(RegisterAction::RecordMacro) => "Record macro into register",
+            Self::AwaitRegister(RegisterAction::ReplayMacro) => "Replay macro from register, @ for last",
+            Self::AwaitRegister(RegisterAction::SetMark) => "Set mark",
+            Self::AwaitRegister(RegisterAction::JumpToMark) => "Jump to mark",
+            Self::WithRegister(_, _) => "Use register",
+            Self::CancelRegister => "Cancel",
+            Self::Count(_) => "Count for the next @, like 3@a",
+            Self::Nop => "Nothing",
+            Self::Autosave => "Save changes",
+        }
+    }
+
+    /// True if the action changes the board or writes the database, so is refused in read-only mode.
+    /// Entering insert mode some other way, like double-clicking, is refused where it happens.
+    fn is_mutating(self) -> bool {
+        matches!(
+            self,
+            Self::Save |
+            Self::Import |
+            Self::DeleteTodo |
+            Self::DeleteMarked |
+            Self::SortList |
+            Self::MoveTodoLeft |
+            Self::MoveTodoRight |
+            Self::MoveTodoUp |
+            Self::MoveTodoDown |
+            Self::MoveTodoTop |
+            Self::MoveTodoBottom |
+            Self::AddTodoAbove |
+            Self::AddTodoBelow |
+            Self::ToggleMark |
+            Self::PasteClipboard |
+            Self::SetMode(Mode::Insert) |
+            Self::InsertStart |
+            Self::Append |
+            Self::ChangeTodo |
+            Self::SendTodoTo |
+            Self::Undo |
+            Self::Redo |
+            Self::AwaitRegister(RegisterAction::SetMark) |
+            Self::WithRegister(RegisterAction::SetMark, _)
+        )
+    }
+
+    /// True if the action keeps a count typed before it, as it is on the way to replaying a macro.
+    fn takes_count(self) -> bool {
+        matches!(
+            self,
+            Self::Count(_) |
+            Self::AwaitRegister(RegisterAction::ReplayMacro) |
+            Self::WithRegister(RegisterAction::ReplayMacro, _)
+        )
+    }
+
+    /// True if the action should be recorded into a macro.
+    /// Actions that control macro recording itself, mouse actions, and those not from key presses, are left out.
+    fn is_recordable(self) -> bool {
+        !matches!(
+            self,
+            Self::Click { .. } |
+            Self::Wheel { .. } |
+            Self::RecordMacro |
+            Self::AwaitRegister(RegisterAction::RecordMacro) |
+            Self::WithRegister(RegisterAction::RecordMacro, _)
+        ) && self.is_key_press()
+    }
+}
+
+/// Action that needs a register, typed as the key following it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum RegisterAction {
+    RecordMacro,
+    ReplayMacro,
+    SetMark,
+    JumpToMark,
 }
 
 /// Current mode of an [`App`] which determines the action keys map to.
@@ -703,6 +3271,89 @@ pub(crate) enum Mode {
     Normal,
     /// Mode when inserting a value in the cell of a todo.
     Insert,
+    /// Mode when fuzzy finding a todo across all todo lists.
+    Finder,
+    /// Mode when selecting a range of todos within a todo list.
+    Visual,
+    /// Mode when choosing a todo list to send the selected todo to.
+    Picker,
+    /// Mode when answering a yes/no question.
+    Confirm,
+    /// Mode when typing a line of text in the bottom row, like a path to export to.
+    Prompt,
+    /// Mode when reading a popup, like the "what's new" screen.
+    Popup,
+}
+
+impl Mode {
+    /// Every mode, in the order shown in help.
+    const ALL: [Mode; 8] = [
+        Mode::Normal,
+        Mode::Insert,
+        Mode::Visual,
+        Mode::Finder,
+        Mode::Picker,
+        Mode::Confirm,
+        Mode::Prompt,
+        Mode::Popup,
+    ];
+
+    /// Name of the mode, as shown in the bottom row.
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Normal => "Normal",
+            Mode::Insert => "Insert",
+            Mode::Finder => "Finder",
+            Mode::Visual => "Visual",
+            Mode::Picker => "Picker",
+            Mode::Confirm => "Confirm",
+            Mode::Prompt => "Prompt",
+            Mode::Popup => "Popup",
+        }
+    }
+
+    /// Actions whose keys are hinted at in the status bar while in the mode, with a label for each group.
+    /// Keys of actions grouped together are shown joined, like "H/L:move".
+    fn key_hints(self) -> &'static [(&'static [Action], &'static str)] {
+        match self {
+            Mode::Normal => &[
+                (&[Action::AddTodoBelow], "add"),
+                (&[Action::ToggleMark], "mark"),
+                (&[Action::DeleteTodo], "del"),
+                (&[Action::MoveTodoLeft, Action::MoveTodoRight], "move"),
+                (&[Action::SetMode(Mode::Insert)], "edit"),
+                (&[Action::ShowHelp], "help"),
+                (&[Action::Quit], "quit"),
+            ],
+            Mode::Insert => &[
+                (&[Action::SetMode(Mode::Normal)], "done"),
+                (&[Action::MoveCursorLeft, Action::MoveCursorRight], "cursor"),
+                (&[Action::InsertNext], "next"),
+            ],
+            Mode::Visual => &[
+                (&[Action::ToggleMark], "mark"),
+                (&[Action::DeleteTodo], "del"),
+                (&[Action::MoveTodoLeft, Action::MoveTodoRight], "move"),
+                (&[Action::YankToClipboard], "yank"),
+                (&[Action::SetMode(Mode::Normal)], "exit"),
+            ],
+            Mode::Finder => &[
+                (&[Action::FinderJump], "jump"),
+                (&[Action::FinderPrev, Action::FinderNext], "select"),
+                (&[Action::SetMode(Mode::Normal)], "cancel"),
+            ],
+            Mode::Picker => &[
+                (&[Action::PickerConfirm], "send"),
+                (&[Action::PickerPrev, Action::PickerNext], "select"),
+                (&[Action::SetMode(Mode::Normal)], "cancel"),
+            ],
+            Mode::Confirm | Mode::Prompt => &[],
+            Mode::Popup => &[
+                (&[Action::PopupScrollUp, Action::PopupScrollDown], "scroll"),
+                (&[Action::SetMode(Mode::Normal)], "close"),
+            ],
+        }
+    }
 }
 
 /// Represents a key press, while in a particular mode, with optional modifiers like shift and ctrl
@@ -716,10 +3367,17 @@ pub(crate) struct KeyPress {
 
 impl KeyPress {
 
+    /// Key press with shift normalized, so keys match their bindings whether or not the terminal reports shift
+    /// like the kitty keyboard protocol does. Shift is implied by uppercase letters, and dropped for other
+    /// characters like '?' since it's part of the character typed. Shift+tab is always back tab.
     pub fn new(mode: Mode, code: KeyCode, modifiers: KeyModifiers) -> Self {
-        let modifiers = match code {
-            KeyCode::Char(c) if c.is_ascii_uppercase() => modifiers | KeyModifiers::SHIFT,
-            _ => modifiers,
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let (code, modifiers) = match code {
+            KeyCode::Char(c) if c.is_ascii_lowercase() && shift => (KeyCode::Char(c.to_ascii_uppercase()), modifiers),
+            KeyCode::Char(c) if c.is_ascii_uppercase() => (code, modifiers | KeyModifiers::SHIFT),
+            KeyCode::Char(c) if !c.is_alphabetic() => (code, modifiers - KeyModifiers::SHIFT),
+            KeyCode::Tab if shift => (KeyCode::BackTab, modifiers),
+            _ => (code, modifiers),
         };
         Self { mode, code, modifiers }
     }
@@ -731,4 +3389,1485 @@ impl KeyPress {
     pub fn code(mode: Mode, code: KeyCode) -> Self {
         Self::new(mode, code, KeyModifiers::empty())
     }
+
+    /// Key and modifiers as shown in help, like "ctrl+d" or "shift+up".
+    /// Shift is left implied for uppercase letters.
+    fn key_name(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::BackTab => "tab".to_string(),
+            KeyCode::PageUp => "pageup".to_string(),
+            KeyCode::PageDown => "pagedown".to_string(),
+            KeyCode::F(n) => format!("f{n}"),
+            code => format!("{code:?}").to_lowercase(),
+        };
+        let mut name = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            name.push_str("ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            name.push_str("alt+");
+        }
+        let implied_shift = matches!(self.code, KeyCode::Char(c) if c.is_ascii_uppercase());
+        if self.code == KeyCode::BackTab || (self.modifiers.contains(KeyModifiers::SHIFT) && !implied_shift) {
+            name.push_str("shift+");
+        }
+        name.push_str(&key);
+        name
+    }
+
+    /// Short name of the key, for hints where space is tight. Arrow keys are drawn as arrows.
+    fn hint_name(&self) -> String {
+        let name = self.key_name();
+        let arrow = match self.code {
+            KeyCode::Left => "←",
+            KeyCode::Right => "→",
+            KeyCode::Up => "↑",
+            KeyCode::Down => "↓",
+            _ => return name,
+        };
+        name.replacen(&format!("{:?}", self.code).to_lowercase(), arrow, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rng, test_dir};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::Buffer;
+    use ratatui::style::Modifier;
+    use unicode_width::UnicodeWidthStr;
+
+    /// Writes a database holding lists of todos by name, and a config using it with `config` added, to a fresh
+    /// directory named after the test. Returns the directory.
+    fn write_test_db(name: &str, config: &str, todo_lists: &[(&str, &[&str])]) -> PathBuf {
+        let dir = test_dir(name);
+        let state = State {
+            todo_lists: todo_lists
+                .iter()
+                .map(|(name, todos)| TodoList {
+                    name: name.to_string(),
+                    todos: todos.iter().map(|&name| Todo::new(name)).collect(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let dbpath = dir.join("db.yml");
+        DbFormat::Yaml.storage(&dbpath.to_string_lossy()).save(&state).unwrap();
+        std::fs::write(dir.join("config.yml"), format!("dbpath: '{}'\n{config}", dbpath.display())).unwrap();
+        dir
+    }
+
+    /// Options using the database and config written to a directory by [`write_test_db`], whatever the environment.
+    fn test_options(dir: &Path) -> Options {
+        Options {
+            dbpath: Some(dir.join("db.yml").to_string_lossy().into_owned()),
+            config_path: Some(dir.join("config.yml").to_string_lossy().into_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// App started on the database and config written to a directory by [`write_test_db`].
+    fn init_app(dir: &Path) -> App {
+        App::init(test_options(dir)).unwrap()
+    }
+
+    fn press(app: &mut App, actions: &[Action]) {
+        for &action in actions {
+            app.update(action).unwrap();
+        }
+    }
+
+    /// Presses a key the way [`App::run`] does, doing nothing if it isn't mapped in the current mode.
+    fn press_key(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+        if let Some(action) = app.key_action(code, modifiers) {
+            app.update(action).unwrap();
+        }
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.update(Action::Input(KeyCode::Char(c))).unwrap();
+        }
+    }
+
+    /// Names of the todos in a list.
+    fn names(app: &App, todo_list_idx: usize) -> Vec<&str> {
+        app.todo_lists[todo_list_idx].todos.iter().map(|todo| todo.name.as_str()).collect()
+    }
+
+    #[test]
+    fn selection_after_removing_todos() {
+        use Action::*;
+        let dir = write_test_db("selection_after_removal", "confirm_delete: false\nfollow_sent_todo: false\n", &[]);
+        let mut app = init_app(&dir);
+        let delete_marked = &[ToggleMark, DeleteMarked, ConfirmYes][..];
+        let archive = &[ToggleMark, DeleteTodo][..];
+        let retype_blank = &[ChangeTodo, SetMode(Mode::Normal)][..];
+        let send_away = &[SendTodoTo, PickerChoose(1)][..];
+        // Todos before, todo selected, actions, todos after, todo selected after.
+        type Case<'a> = (&'a [&'a str], usize, &'a [Action], &'a [&'a str], usize);
+        let cases: &[Case] = &[
+            (&["a", "b", "c"], 0, &[DeleteTodo], &["b", "c"], 0),
+            (&["a", "b", "c"], 1, &[DeleteTodo], &["a", "c"], 1),
+            (&["a", "b", "c"], 2, &[DeleteTodo], &["a", "b"], 1),
+            (&["a"], 0, &[DeleteTodo], &[], 0),
+            (&["a", "b", "c", "d"], 0, &[SetMode(Mode::Visual), MoveDown, DeleteTodo], &["c", "d"], 0),
+            (&["a", "b", "c", "d"], 1, &[SetMode(Mode::Visual), MoveDown, DeleteTodo], &["a", "d"], 1),
+            (&["a", "b", "c", "d"], 3, &[SetMode(Mode::Visual), MoveUp, DeleteTodo], &["a", "b"], 1),
+            (&["a", "b"], 0, &[SetMode(Mode::Visual), MoveDown, DeleteTodo], &[], 0),
+            (&["a", "b", "c"], 0, archive, &["b", "c"], 0),
+            (&["a", "b", "c"], 1, archive, &["a", "c"], 1),
+            (&["a", "b", "c"], 2, archive, &["a", "b"], 1),
+            (&["a"], 0, archive, &[], 0),
+            (&["a", "b", "c"], 0, delete_marked, &["b", "c"], 0),
+            (&["a", "b", "c"], 1, delete_marked, &["a", "c"], 1),
+            (&["a", "b", "c"], 2, delete_marked, &["a", "b"], 1),
+            (&["a"], 0, delete_marked, &[], 0),
+            (&["a", "b", "c"], 0, retype_blank, &["b", "c"], 0),
+            (&["a", "b", "c"], 1, retype_blank, &["a", "c"], 1),
+            (&["a", "b", "c"], 2, retype_blank, &["a", "b"], 1),
+            (&["a"], 0, retype_blank, &[], 0),
+            (&["a", "b", "c"], 0, send_away, &["b", "c"], 0),
+            (&["a", "b", "c"], 1, send_away, &["a", "c"], 1),
+            (&["a", "b", "c"], 2, send_away, &["a", "b"], 1),
+            (&["a"], 0, send_away, &[], 0),
+            (&["a", "b", "c"], 0, &[DeleteTodo, Undo], &["a", "b", "c"], 0),
+            (&["a", "b", "c"], 1, &[DeleteTodo, Undo], &["a", "b", "c"], 1),
+            (&["a", "b", "c"], 2, &[DeleteTodo, Undo], &["a", "b", "c"], 2),
+            (&["a"], 0, &[DeleteTodo, Undo], &["a"], 0),
+        ];
+        for &(before, selected, actions, after, selected_after) in cases {
+            app.todo_lists = State::default().todo_lists;
+            app.todo_lists[0].todos = before.iter().map(|&name| Todo::new(name)).collect();
+            app.selection = Selection { todo: selected, ..Selection::default() };
+            press(&mut app, actions);
+            assert_eq!(app.mode, Mode::Normal);
+            assert_eq!(names(&app, 0), after, "{actions:?} on {before:?} at {selected}");
+            assert_eq!(app.selection.todo_list, 0, "{actions:?} on {before:?} at {selected}");
+            assert_eq!(app.selection.todo, selected_after, "{actions:?} on {before:?} at {selected}");
+        }
+    }
+
+    #[test]
+    fn whats_new_lists_versions_since_the_last_seen() {
+        let lines = whats_new("0.1").unwrap();
+        assert_eq!(lines[0], "0.2:");
+        assert!(lines[1..].iter().all(|line| line.starts_with("• ")));
+        assert_eq!(lines.len(), 1 + CHANGELOG[0].1.len());
+        assert_eq!(whats_new(APP_VERSION), None);
+        assert_eq!(whats_new("9.9"), None);
+        assert_eq!(whats_new("0.2.0"), None);
+    }
+
+    #[test]
+    fn whats_new_is_shown_once_after_upgrading() {
+        let dir = write_test_db("whats_new_upgrade", "", &[]);
+        std::fs::write(dir.join("db.yml"), "version: '0.1'\ntodo_lists:\n- name: Todo\n  todos:\n  - name: Old\n").unwrap();
+        let mut app = init_app(&dir);
+        assert_eq!(app.mode, Mode::Popup);
+        assert_eq!(app.popup.title, format!("What's new in tdi {APP_VERSION}"));
+        assert!(app.needs_saving);
+
+        press(&mut app, &[Action::SetMode(Mode::Normal), Action::Save]);
+        assert!(std::fs::read_to_string(dir.join("db.yml")).unwrap().contains(&format!("version: '{APP_VERSION}'")));
+        let app = init_app(&dir);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(names(&app, 0), ["Old"]);
+    }
+
+    #[test]
+    fn whats_new_is_not_shown_on_a_fresh_install() {
+        let dir = write_test_db("whats_new_fresh", "", &[]);
+        std::fs::remove_file(dir.join("db.yml")).unwrap();
+        let app = init_app(&dir);
+        assert_eq!(app.mode, Mode::Normal);
+        assert!(app.message.is_some_and(|message| message.text.starts_with("Welcome")));
+    }
+
+    #[test]
+    fn whats_new_is_not_shown_after_downgrading() {
+        let dir = write_test_db("whats_new_downgrade", "", &[]);
+        std::fs::write(dir.join("db.yml"), "version: '9.9'\ntodo_lists: []\n").unwrap();
+        let err = App::init(test_options(&dir)).err().unwrap();
+        assert!(format!("{err:#}").contains("newer than this tdi"));
+        assert_eq!(std::fs::read_to_string(dir.join("db.yml")).unwrap(), "version: '9.9'\ntodo_lists: []\n");
+    }
+
+    /// List and todo selected.
+    fn selected(app: &App) -> (usize, usize) {
+        (app.selection.todo_list, app.selection.todo)
+    }
+
+    #[test]
+    fn undo_and_redo_return_the_selection_to_each_change() {
+        use Action::*;
+        let dir = write_test_db("undo_selection", "", &[("Todo", &["a", "b", "c"]), ("Backlog", &["x", "y"])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[MoveBottom, ToggleMark]);
+        press(&mut app, &[MoveRight, MoveBottom, MoveTodoUp]);
+        press(&mut app, &[MoveLeft, MoveTop, MoveTodoRight]);
+        assert_eq!((names(&app, 0), names(&app, 1)), (vec!["b", "c"], vec!["a", "y", "x"]));
+        assert_eq!(selected(&app), (1, 0));
+
+        press(&mut app, &[Undo]);
+        assert_eq!((names(&app, 0), names(&app, 1)), (vec!["a", "b", "c"], vec!["y", "x"]));
+        assert_eq!(selected(&app), (0, 0));
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 1), ["x", "y"]);
+        assert_eq!(selected(&app), (1, 1));
+        press(&mut app, &[Undo]);
+        assert!(!app.todo_lists[0].todos[2].marked);
+        assert_eq!(selected(&app), (0, 2));
+        press(&mut app, &[Undo]);
+        assert_eq!(selected(&app), (0, 2));
+
+        press(&mut app, &[Redo]);
+        assert!(app.todo_lists[0].todos[2].marked);
+        assert_eq!(selected(&app), (1, 1));
+        press(&mut app, &[Redo]);
+        assert_eq!(names(&app, 1), ["y", "x"]);
+        assert_eq!(selected(&app), (0, 0));
+        press(&mut app, &[Redo]);
+        assert_eq!((names(&app, 0), names(&app, 1)), (vec!["b", "c"], vec!["a", "y", "x"]));
+        assert_eq!(selected(&app), (1, 0));
+    }
+
+    #[test]
+    fn undo_clamps_a_selection_the_board_no_longer_has() {
+        let dir = write_test_db("undo_clamp", "", &[("Todo", &["a", "b", "c"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        app.history.record(State {
+            todo_lists: vec![TodoList { name: "Todo".to_string(), todos: vec![Todo::new("a")] }],
+            selection: Selection { todo_list: 3, todo: 7, ..Selection::default() },
+            ..Default::default()
+        });
+        press(&mut app, &[Action::Undo]);
+        assert_eq!(selected(&app), (0, 0));
+        press(&mut app, &[Action::Redo]);
+        assert_eq!(names(&app, 0), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn undo_removes_a_todo_added_and_typed_in_one_step() {
+        use Action::*;
+        let dir = write_test_db("undo_insert_add", "", &[("Todo", &["a", "b"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        for add in [AddTodoBelow, AddTodoAbove] {
+            press(&mut app, &[add]);
+            type_text(&mut app, "a forty character long todo, typed in full");
+            press(&mut app, &[SetMode(Mode::Normal)]);
+            assert_eq!(names(&app, 0).len(), 3);
+            press(&mut app, &[Undo]);
+            assert_eq!(names(&app, 0), ["a", "b"], "{add:?}");
+            assert_eq!(selected(&app), (0, 0));
+        }
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["a", "b"]);
+    }
+
+    #[test]
+    fn undo_reverts_an_edit_in_one_step() {
+        use Action::*;
+        let dir = write_test_db("undo_insert_edit", "", &[("Todo", &["Buy milk"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[SetMode(Mode::Insert)]);
+        type_text(&mut app, " and eggs");
+        press(&mut app, &[MoveCursorStart, Input(KeyCode::Delete), Input(KeyCode::Delete), Input(KeyCode::Delete)]);
+        type_text(&mut app, "Sell");
+        press(&mut app, &[SetMode(Mode::Normal)]);
+        assert_eq!(names(&app, 0), ["Sell milk and eggs"]);
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["Buy milk"]);
+        press(&mut app, &[Redo]);
+        assert_eq!(names(&app, 0), ["Sell milk and eggs"]);
+    }
+
+    #[test]
+    fn each_todo_started_with_enter_is_its_own_undo_step() {
+        use Action::*;
+        let dir = write_test_db("undo_insert_next", "", &[("Todo", &[]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[AddTodoBelow]);
+        type_text(&mut app, "first");
+        press(&mut app, &[InsertNext]);
+        type_text(&mut app, "second");
+        press(&mut app, &[SetMode(Mode::Normal)]);
+        assert_eq!(names(&app, 0), ["first", "second"]);
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["first"]);
+        press(&mut app, &[Undo]);
+        assert!(names(&app, 0).is_empty());
+    }
+
+    /// Name of the selected todo.
+    fn selected_name(app: &App) -> &str {
+        let (todo_list_idx, todo_idx) = app.selected_todo().unwrap();
+        &app.todo_lists[todo_list_idx].todos[todo_idx].name
+    }
+
+    #[test]
+    fn editing_multi_byte_text() {
+        use Action::*;
+        let dir = write_test_db("edit_multi_byte", "", &[("Todo", &[]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[AddTodoBelow]);
+        type_text(&mut app, "café 日本 🎉");
+        assert_eq!(app.selection.char, 9);
+        press(&mut app, &[Input(KeyCode::Backspace)]);
+        assert_eq!(selected_name(&app), "café 日本 ");
+        press(&mut app, &[MoveCursorLeft, MoveCursorLeft, MoveCursorLeft, Input(KeyCode::Delete)]);
+        assert_eq!(selected_name(&app), "café 本 ");
+        type_text(&mut app, "X");
+        assert_eq!((selected_name(&app), app.selection.char), ("café X本 ", 6));
+        press(&mut app, &[MoveCursorStart, MoveCursorRight, MoveCursorRight, MoveCursorRight, MoveCursorRight]);
+        press(&mut app, &[Input(KeyCode::Backspace)]);
+        assert_eq!((selected_name(&app), app.selection.char), ("caf X本 ", 3));
+        press(&mut app, &[MoveCursorEnd]);
+        assert_eq!(app.selection.char, 7);
+        type_text(&mut app, "ü");
+        press(&mut app, &[Input(KeyCode::Delete), MoveCursorRight]);
+        assert_eq!((selected_name(&app), app.selection.char), ("caf X本 ü", 8));
+        press(&mut app, &[SetMode(Mode::Normal)]);
+        assert_eq!(names(&app, 0), ["caf X本 ü"]);
+    }
+
+    #[test]
+    fn deleting_words_and_lines_of_multi_byte_text() {
+        use Action::*;
+        let dir = write_test_db("delete_multi_byte", "", &[("Todo", &["日本語 テスト 🎉 fin"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[SetMode(Mode::Insert), MoveCursorWordBackward, MoveCursorWordBackward]);
+        assert_eq!(app.selection.char, 8);
+        press(&mut app, &[DeleteWordBackward]);
+        assert_eq!((selected_name(&app), app.selection.char), ("日本語 🎉 fin", 4));
+        press(&mut app, &[MoveCursorWordForward, DeleteToEnd]);
+        assert_eq!(selected_name(&app), "日本語 🎉 ");
+        press(&mut app, &[MoveCursorLeft, MoveCursorLeft, DeleteToStart]);
+        assert_eq!((selected_name(&app), app.selection.char), ("🎉 ", 0));
+        press(&mut app, &[Input(KeyCode::Backspace), Input(KeyCode::Delete)]);
+        assert_eq!(selected_name(&app), " ");
+    }
+
+    #[test]
+    fn cursor_moves_and_deletes_whole_grapheme_clusters() {
+        use Action::*;
+        let name = "👩\u{200d}💻 pair with Ana";
+        let dir = write_test_db("grapheme_clusters", "", &[("Todo", &[name]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[SetMode(Mode::Insert), MoveCursorStart, MoveCursorRight]);
+        assert_eq!(app.selection.char, 1);
+        press(&mut app, &[MoveCursorLeft]);
+        assert_eq!(app.selection.char, 0);
+        press(&mut app, &[Input(KeyCode::Delete)]);
+        assert_eq!(selected_name(&app), " pair with Ana");
+
+        press(&mut app, &[MoveCursorEnd]);
+        type_text(&mut app, " 🇫🇷");
+        assert_eq!(app.selection.char, grapheme_count(" pair with Ana 🇫🇷"));
+        press(&mut app, &[Input(KeyCode::Backspace)]);
+        assert_eq!(selected_name(&app), " pair with Ana ");
+
+        // A combining accent typed after its letter joins it, and goes with it.
+        type_text(&mut app, "e\u{301}!");
+        assert_eq!(app.selection.char, grapheme_count(" pair with Ana é!"));
+        press(&mut app, &[MoveCursorLeft, Input(KeyCode::Backspace)]);
+        assert_eq!(selected_name(&app), " pair with Ana !");
+    }
+
+    /// Draws the app on a terminal of the given size, and returns what ends up on it.
+    fn draw(app: &mut App, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    /// Text of a row of the screen, skipping the cells wide characters spill into.
+    fn row_text(buffer: &Buffer, y: u16) -> String {
+        let mut text = String::new();
+        let mut skip = 0;
+        for x in 0..buffer.area.width {
+            let symbol = buffer[(x, y)].symbol();
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            skip = symbol.width().saturating_sub(1);
+            text.push_str(symbol);
+        }
+        text
+    }
+
+    #[test]
+    fn long_todos_are_cut_off_inside_their_list() {
+        let long = "a todo much too long to fit in its list 日本語日本語日本語 end";
+        let dir = write_test_db("long_todos", "", &[("Todo", &[long, "short"]), ("Backlog", &["日本語日本語日本語日本語日本語"])]);
+        let mut app = init_app(&dir);
+        let buffer = draw(&mut app, 40, 8);
+        assert_eq!(row_text(&buffer, 1), "│ • a todo much t… ││ • 日本語日本語…  │");
+        assert_eq!(row_text(&buffer, 2), "│ • short          ││                  │");
+        for y in 1..6 {
+            for x in [0, 19, 20, 39] {
+                assert_eq!(buffer[(x, y)].symbol(), "│", "border at {x},{y}");
+            }
+        }
+
+        // The whole todo can still be read by editing it, scrolled to the cursor.
+        press(&mut app, &[Action::Append]);
+        let buffer = draw(&mut app, 40, 8);
+        assert_eq!(row_text(&buffer, 1), "│ • 語日本語 end   ││ • 日本語日本語…  │");
+        press(&mut app, &[Action::MoveCursorStart]);
+        let buffer = draw(&mut app, 40, 8);
+        assert_eq!(row_text(&buffer, 1), "│ • a todo much t… ││ • 日本語日本語…  │");
+    }
+
+    #[test]
+    fn marked_todos_are_crossed_out_and_dimmed() {
+        let dir = write_test_db("marked_style", "", &[("Todo", &["done", "open"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ToggleMark]);
+        assert!(app.todo_lists[0].todos[0].marked);
+        let theme = Theme::default();
+
+        // Dimming is left off while selected, so the todo stays readable on the selection's background.
+        let buffer = draw(&mut app, 40, 8);
+        let (done, open) = (&buffer[(4, 1)], &buffer[(4, 2)]);
+        assert_eq!((done.symbol(), done.fg, done.modifier), ("d", theme.fg_marked, Modifier::CROSSED_OUT));
+        assert_eq!((open.symbol(), open.modifier), ("o", Modifier::empty()));
+
+        press(&mut app, &[Action::MoveDown]);
+        let buffer = draw(&mut app, 40, 8);
+        let (done, open) = (&buffer[(4, 1)], &buffer[(4, 2)]);
+        assert_eq!((done.fg, done.modifier), (theme.fg_marked, Modifier::CROSSED_OUT | Modifier::DIM));
+        assert_eq!((open.fg, open.modifier), (theme.fg_selected, Modifier::empty()));
+    }
+
+    #[test]
+    fn marked_style_comes_from_the_theme() {
+        let dir = write_test_db("marked_style_theme", "theme:\n  marked_style: italic\n", &[("Todo", &["done", "open"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ToggleMark, Action::MoveDown]);
+        let buffer = draw(&mut app, 40, 8);
+        assert_eq!(buffer[(4, 1)].modifier, Modifier::ITALIC);
+
+        let dir = write_test_db("marked_style_none", "theme:\n  marked_style: none\n", &[("Todo", &["done", "open"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ToggleMark, Action::MoveDown]);
+        let buffer = draw(&mut app, 40, 8);
+        assert_eq!(buffer[(4, 1)].modifier, Modifier::empty());
+    }
+
+    #[test]
+    fn tiny_terminals_show_a_placeholder() {
+        let dir = write_test_db("tiny", "", &[("Todo", &["a todo"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        for mode_actions in [&[][..], &[Action::Append], &[Action::ShowHelp]] {
+            press(&mut app, mode_actions);
+            for (width, height) in [(0, 0), (1, 1), (200, 2), (10, 3), (19, 40), (20, 3), (10, 200)] {
+                let buffer = draw(&mut app, width, height);
+                let screen: String = (0..height).map(|y| row_text(&buffer, y)).collect();
+                if width >= 4 && height >= 2 {
+                    assert!(screen.contains("terminal"), "{width}x{height}: {screen:?}");
+                }
+                assert!(!screen.contains("a todo"), "{width}x{height}: {screen:?}");
+                assert!(app.list_areas.is_empty());
+            }
+            press(&mut app, &[Action::SetMode(Mode::Normal)]);
+        }
+        let buffer = draw(&mut app, 200, 2);
+        assert_eq!(row_text(&buffer, 0).trim(), "terminal too small (need at least 20x4)");
+    }
+
+    #[test]
+    fn smallest_terminal_shows_the_board() {
+        let dir = write_test_db("smallest", "", &[("Todo", &["a todo much too long"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        let buffer = draw(&mut app, 20, 4);
+        assert_eq!(row_text(&buffer, 1), "│ • a t… ││        │");
+
+        // The cursor stays inside the list, however far along the todo it is.
+        press(&mut app, &[Action::Append]);
+        let mut terminal = Terminal::new(TestBackend::new(20, 4)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let cursor = terminal.get_cursor_position().unwrap();
+        assert!(cursor.x >= 2 && cursor.x <= 8 && cursor.y == 1, "{cursor:?}");
+    }
+
+    #[test]
+    fn clicks_select_what_is_under_them() {
+        use Action::*;
+        let dir = write_test_db("clicks", "", &[("Todo", &["one", "two", "three"]), ("Backlog", &["four"])]);
+        let mut app = init_app(&dir);
+        draw(&mut app, 40, 10);
+        type Case = ((u16, u16), (usize, usize));
+        let cases: &[Case] = &[
+            ((5, 2), (0, 1)),  // On a todo
+            ((17, 3), (0, 2)), // Past the end of a todo, on its row
+            ((5, 7), (0, 2)),  // Below the todos, selecting the list only
+            ((25, 1), (1, 0)), // In the other list
+            ((5, 7), (0, 0)),  // Below the todos of the other list
+            ((0, 1), (0, 0)),  // On the border, beside a todo
+            ((5, 9), (0, 0)),  // On the status bar, doing nothing
+            ((5, 0), (0, 0)),  // On the title
+        ];
+        for &((column, row), expected) in cases {
+            press(&mut app, &[Click { column, row }]);
+            app.last_click = None;
+            assert_eq!(selected(&app), expected, "click at {column},{row}");
+        }
+    }
+
+    #[test]
+    fn clicks_account_for_the_header_and_scrolling() {
+        use Action::*;
+        let todos: Vec<String> = (0..20).map(|i| format!("todo {i}")).collect();
+        let todos: Vec<&str> = todos.iter().map(String::as_str).collect();
+        let dir = write_test_db("clicks_scrolled", "header: true\n", &[("Todo", &todos), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[MoveBottom]);
+        draw(&mut app, 40, 10);
+        // Rows are the header, the title, 6 todos from todo 14 on, the border and the status bar.
+        press(&mut app, &[Click { column: 5, row: 2 }]);
+        assert_eq!(selected(&app), (0, 14));
+        press(&mut app, &[Click { column: 5, row: 1 }]);
+        assert_eq!(selected(&app), (0, 14));
+    }
+
+    #[test]
+    fn double_click_edits_where_clicked() {
+        use Action::*;
+        let dir = write_test_db("double_click", "", &[("Todo", &["one", "two"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        draw(&mut app, 40, 10);
+        press(&mut app, &[Click { column: 6, row: 2 }, Click { column: 6, row: 2 }]);
+        assert_eq!((app.mode, selected(&app), app.selection.char), (Mode::Insert, (0, 1), 2));
+    }
+
+    #[test]
+    fn clicks_do_nothing_outside_normal_mode() {
+        use Action::*;
+        let dir = write_test_db("clicks_ignored", "", &[("Todo", &["one", "two"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[SetMode(Mode::Visual)]);
+        draw(&mut app, 40, 10);
+        press(&mut app, &[Click { column: 5, row: 2 }]);
+        assert_eq!(selected(&app), (0, 0));
+    }
+
+    #[test]
+    fn ctrl_c_quits_in_every_mode() {
+        let dir = write_test_db("ctrl_c", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        for mode in Mode::ALL {
+            app.mode = mode;
+            assert_eq!(app.key_action(KeyCode::Char('c'), KeyModifiers::CONTROL), Some(Action::Quit), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn quitting_while_editing_saves_the_edit() {
+        use Action::*;
+        let dir = write_test_db("quit_editing", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Append]);
+        type_text(&mut app, " more");
+        press(&mut app, &[AddTodoBelow]);
+        press(&mut app, &[Quit]);
+        assert!(app.quit);
+        assert_eq!(app.mode, Mode::Normal);
+        // The blank todo being edited is dropped, as leaving insert mode does.
+        assert_eq!(names(&init_app(&dir), 0), ["one more"]);
+    }
+
+    #[test]
+    fn quitting_again_after_quitting_is_harmless() {
+        let dir = write_test_db("quit_twice", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ToggleMark, Action::Quit, Action::Quit, Action::Quit]);
+        assert!(app.quit);
+        assert!(init_app(&dir).todo_lists[0].todos[0].marked);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn terminate_signals_are_caught() {
+        watch_terminate_signals().unwrap();
+        for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGHUP] {
+            TERMINATE.store(false, Ordering::Relaxed);
+            signal_hook::low_level::raise(signal).unwrap();
+            assert!(TERMINATE.load(Ordering::Relaxed), "signal {signal}");
+        }
+        TERMINATE.store(false, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn terminating_saves_even_mid_edit() {
+        use Action::*;
+        let dir = write_test_db("terminate", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Append]);
+        type_text(&mut app, " more");
+        press(&mut app, &[Terminate]);
+        assert!(app.quit);
+        assert_eq!(names(&init_app(&dir), 0), ["one more"]);
+    }
+
+    #[test]
+    fn terminating_quits_even_if_saving_fails() {
+        let dir = write_test_db("terminate_failing", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ToggleMark]);
+        std::fs::remove_file(dir.join("db.yml")).unwrap();
+        std::fs::create_dir(dir.join("db.yml")).unwrap();
+        assert!(app.update(Action::Terminate).is_err());
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn db_given_is_created_with_its_directories() {
+        let dir = write_test_db("db_given", "", &[("Todo", &["in the config's db"]), ("Backlog", &[])]);
+        let dbpath = dir.join("not/yet/db.yml");
+        let options = Options { dbpath: Some(dbpath.to_string_lossy().into_owned()), ..test_options(&dir) };
+        let mut app = App::init(options.clone()).unwrap();
+        assert!(!names(&app, 0).contains(&"in the config's db"));
+        press(&mut app, &[Action::AddTodoAbove]);
+        type_text(&mut app, "new");
+        press(&mut app, &[Action::Quit]);
+        assert!(dbpath.exists());
+        assert_eq!(names(&App::init(options).unwrap(), 0)[0], "new");
+        assert_eq!(names(&init_app(&dir), 0), ["in the config's db"]);
+    }
+
+    #[test]
+    fn failing_to_save_keeps_the_changes_and_the_database() {
+        let dir = write_test_db("save_failing", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let before = std::fs::read_to_string(dir.join("db.yml")).unwrap();
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ToggleMark]);
+        std::fs::create_dir(dir.join(".db.yml.tmp")).unwrap();
+        assert!(app.update(Action::Save).is_err());
+        assert!(app.update(Action::Quit).is_err());
+        assert!(!app.quit && app.needs_saving);
+        assert_eq!(std::fs::read_to_string(dir.join("db.yml")).unwrap(), before);
+
+        std::fs::remove_dir(dir.join(".db.yml.tmp")).unwrap();
+        press(&mut app, &[Action::Quit]);
+        assert!(init_app(&dir).todo_lists[0].todos[0].marked);
+    }
+
+    #[test]
+    fn config_toml_is_used_over_config_yml() {
+        let dir = test_dir("config_file_in");
+        assert_eq!(config_file_in(&dir), dir.join("config.yml"));
+        std::fs::write(dir.join("config.yml"), "").unwrap();
+        assert_eq!(config_file_in(&dir), dir.join("config.yml"));
+        std::fs::write(dir.join("config.toml"), "").unwrap();
+        assert_eq!(config_file_in(&dir), dir.join("config.toml"));
+        std::fs::remove_file(dir.join("config.yml")).unwrap();
+        assert_eq!(config_file_in(&dir), dir.join("config.toml"));
+    }
+
+    #[test]
+    fn toml_and_yaml_configs_read_the_same() {
+        let dir = test_dir("config_toml");
+        std::fs::write(dir.join("config.yml"), "dbpath: /tmp/db.yml\nwrap_todos: true\nautosave: {debounce: 5}\n").unwrap();
+        std::fs::write(dir.join("config.toml"), "dbpath = '/tmp/db.yml'\nwrap_todos = true\nautosave = { debounce = 5 }\n").unwrap();
+        let yaml = load_app_config(Some(&dir.join("config.yml").to_string_lossy()), None).unwrap();
+        let toml = load_app_config(Some(&dir.join("config.toml").to_string_lossy()), None).unwrap();
+        assert_eq!(yaml, toml);
+        assert!(toml.wrap_todos);
+        assert_eq!(toml.autosave, Autosave::Debounce(5));
+    }
+
+    #[test]
+    fn invalid_configs_say_where_the_problem_is() {
+        let dir = test_dir("config_invalid");
+        for (file_name, contents, position) in [
+            ("config.toml", "dbpath = '/tmp/db.yml'\nwrap_todos = yes\n", "line 2, column 14"),
+            ("config.toml", "dbpath = '/tmp/db.yml'\n[theme\n", "line 2, column 7"),
+            ("config.yml", "dbpath: /tmp/db.yml\nwrap_todos: [\n", "line 2 column 13"),
+        ] {
+            let path = dir.join(file_name);
+            std::fs::write(&path, contents).unwrap();
+            let err = format!("{:#}", load_app_config(Some(&path.to_string_lossy()), None).unwrap_err());
+            assert!(err.starts_with(&format!("Invalid config file {}", path.display())), "{err}");
+            assert!(err.contains(position), "{position} in {err}");
+        }
+    }
+
+    #[test]
+    fn importing_markdown_is_one_undo_step() {
+        use Action::*;
+        let dir = write_test_db("import_undo", "", &[("Todo", &["Buy milk"]), ("Backlog", &[])]);
+        let markdown_path = dir.join("import.md");
+        std::fs::write(&markdown_path, "- [ ] Buy milk\n- [ ] Call Ana\n## Backlog\n- [x] Plan trip\n## Someday\n- [ ] Learn Rust\n").unwrap();
+        let mut app = init_app(&dir);
+        press(&mut app, &[Import]);
+        app.prompt.as_mut().unwrap().text = markdown_path.to_string_lossy().into_owned();
+        press(&mut app, &[SubmitPrompt]);
+        assert_eq!((names(&app, 0), names(&app, 1), names(&app, 2)), (vec!["Buy milk", "Call Ana"], vec!["Plan trip"], vec!["Learn Rust"]));
+        assert!(app.message.as_ref().unwrap().text.ends_with("skipping 1 already there"));
+
+        press(&mut app, &[Undo]);
+        assert_eq!(app.todo_lists.len(), 2);
+        assert_eq!((names(&app, 0), names(&app, 1)), (vec!["Buy milk"], vec![]));
+        press(&mut app, &[Redo]);
+        assert_eq!(app.todo_lists.len(), 3);
+    }
+
+    #[test]
+    fn undo_history_survives_a_restart() {
+        use Action::*;
+        let dir = write_test_db("history_restart", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[AddTodoBelow]);
+        type_text(&mut app, "two");
+        press(&mut app, &[SetMode(Mode::Normal), Save]);
+        assert!(!dir.join("db.yml.undo").exists(), "history is only written when quitting");
+        press(&mut app, &[Quit]);
+
+        let mut app = init_app(&dir);
+        assert_eq!(names(&app, 0), ["one", "two"]);
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["one"]);
+        press(&mut app, &[Redo]);
+        assert_eq!(names(&app, 0), ["one", "two"]);
+    }
+
+    #[test]
+    fn undo_history_of_another_database_or_garbage_is_ignored() {
+        use Action::*;
+        let dir = write_test_db("history_stale", "", &[("Todo", &["one"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[ToggleMark, Quit]);
+        assert!(dir.join("db.yml.undo").exists());
+
+        // The database changed behind tdi's back, so the history no longer applies to it.
+        let mut state = State::default();
+        state.todo_lists[0].todos.push(Todo::new("changed elsewhere"));
+        DbFormat::Yaml.storage(&dir.join("db.yml").to_string_lossy()).save(&state).unwrap();
+        let mut app = init_app(&dir);
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["changed elsewhere"]);
+
+        std::fs::write(dir.join("db.yml.undo"), "db_hash: [not, a, hash\n").unwrap();
+        let mut app = init_app(&dir);
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["changed elsewhere"]);
+        assert!(!app.needs_saving);
+    }
+
+    #[test]
+    fn macros_replay_what_was_recorded() {
+        use Action::*;
+        let dir = write_test_db("macros_replay", "", &[("Todo", &["a", "b", "c", "d"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[RecordMacro, WithRegister(RegisterAction::RecordMacro, 'a'), MoveTodoDown, RecordMacro]);
+        assert_eq!(names(&app, 0), ["b", "a", "c", "d"]);
+        assert_eq!(app.macros[&'a'], [MoveTodoDown]);
+        press(&mut app, &[AwaitRegister(RegisterAction::ReplayMacro), WithRegister(RegisterAction::ReplayMacro, 'a')]);
+        assert_eq!(names(&app, 0), ["b", "c", "a", "d"]);
+        press(&mut app, &[AwaitRegister(RegisterAction::ReplayMacro), WithRegister(RegisterAction::ReplayMacro, '@')]);
+        assert_eq!(names(&app, 0), ["b", "c", "d", "a"]);
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn counts_multiply_macro_replays() {
+        use Action::*;
+        let dir = write_test_db("macro_counts", "", &[("Todo", &["a", "b", "c", "d", "e", "f"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        assert_eq!(app.key_action(KeyCode::Char('3'), KeyModifiers::NONE), Some(Count(3)));
+        let replay = |register| [AwaitRegister(RegisterAction::ReplayMacro), WithRegister(RegisterAction::ReplayMacro, register)];
+        press(&mut app, &[RecordMacro, WithRegister(RegisterAction::RecordMacro, 'a'), MoveTodoDown, RecordMacro]);
+        press(&mut app, &[Count(2)]);
+        press(&mut app, &replay('a'));
+        assert_eq!(names(&app, 0), ["b", "c", "d", "a", "e", "f"]);
+        // The count is used up by the replay, so @@ replays once.
+        press(&mut app, &replay('@'));
+        assert_eq!(names(&app, 0), ["b", "c", "d", "e", "a", "f"]);
+        // Other keys drop the count, and a leading 0 counts for nothing.
+        press(&mut app, &[Count(3), MoveUp, MoveDown]);
+        press(&mut app, &replay('a'));
+        assert_eq!(names(&app, 0), ["b", "c", "d", "e", "f", "a"]);
+        press(&mut app, &[Count(0), Count(2), MoveTop, Count(1), Count(0)]);
+        assert_eq!(app.count, Some(10));
+        press(&mut app, &replay('@'));
+        assert_eq!(names(&app, 0), ["c", "d", "e", "f", "a", "b"]);
+        assert_eq!(app.count, None);
+    }
+
+    #[test]
+    fn macros_replaying_macros_are_not_followed() {
+        use Action::*;
+        let dir = write_test_db("macro_recursion", "", &[("Todo", &["a", "b", "c", "d"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        let replay = |register| [AwaitRegister(RegisterAction::ReplayMacro), WithRegister(RegisterAction::ReplayMacro, register)];
+        press(&mut app, &[RecordMacro, WithRegister(RegisterAction::RecordMacro, 'a'), MoveTodoDown]);
+        press(&mut app, &replay('a'));
+        press(&mut app, &[RecordMacro]);
+        assert_eq!(names(&app, 0), ["b", "a", "c", "d"]);
+        assert_eq!(app.macros[&'a'], [MoveTodoDown, replay('a')[0], replay('a')[1]]);
+        // Replaying itself is where it stops, rather than looping.
+        press(&mut app, &replay('a'));
+        assert_eq!(names(&app, 0), ["b", "c", "a", "d"]);
+        // So is replaying another macro.
+        press(&mut app, &[RecordMacro, WithRegister(RegisterAction::RecordMacro, 'b'), MoveUp]);
+        press(&mut app, &replay('a'));
+        press(&mut app, &[RecordMacro, MoveDown]);
+        assert_eq!(names(&app, 0), ["b", "a", "c", "d"]);
+        assert_eq!(app.macros[&'b'], [MoveUp, replay('a')[0], replay('a')[1]]);
+        press(&mut app, &replay('b'));
+        assert_eq!(names(&app, 0), ["b", "a", "c", "d"]);
+        assert_eq!(selected(&app), (0, 2));
+    }
+
+    #[test]
+    fn finder_jumps_to_the_todo_found() {
+        let dir = write_test_db("finder_jumps", "", &[("Todo", &["walk dog", "buy milk"]), ("Backlog", &["fix bike", "buy bread"])]);
+        let mut app = init_app(&dir);
+        press_key(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+        assert_eq!(app.mode, Mode::Finder);
+        for c in "bread".chars() {
+            press_key(&mut app, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        press_key(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(selected(&app), (1, 1));
+        // Nothing matching leaves the selection be.
+        press_key(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+        for c in "xyz".chars() {
+            press_key(&mut app, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        press_key(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(selected(&app), (1, 1));
+    }
+
+    /// Name of the only todo in each database backup in a directory, from db.yml.1 on, until one is missing.
+    fn backed_up(dir: &Path) -> Vec<String> {
+        (1..)
+            .map(|n| dir.join(format!("db.yml.{n}")))
+            .take_while(|path| path.exists())
+            .map(|path| DbFormat::Yaml.storage(&path.to_string_lossy()).load().unwrap().todo_lists[0].todos[0].name.clone())
+            .collect()
+    }
+
+    /// Saves the app with its only todo renamed.
+    fn save_as(app: &mut App, name: &str) {
+        app.todo_lists[0].todos[0].name = name.to_string();
+        app.write_db().unwrap();
+    }
+
+    #[test]
+    fn saving_keeps_the_previous_versions_in_order() {
+        let dir = write_test_db("backups_rotate", "backup_count: 3\n", &[("Todo", &["v0"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        for n in 1..=5 {
+            save_as(&mut app, &format!("v{n}"));
+        }
+        assert_eq!(backed_up(&dir), ["v4", "v3", "v2"]);
+
+        // Saving the same board again keeps the backups as they are.
+        app.write_db().unwrap();
+        assert_eq!(backed_up(&dir), ["v4", "v3", "v2"]);
+
+        // Lowering the count drops the oldest.
+        app.config.backup_count = 1;
+        save_as(&mut app, "v6");
+        assert_eq!(backed_up(&dir), ["v5"]);
+
+        app.config.backup_count = 0;
+        save_as(&mut app, "v7");
+        assert_eq!(backed_up(&dir), ["v5"]);
+    }
+
+    #[test]
+    fn no_backups_are_kept_with_a_count_of_zero() {
+        let dir = write_test_db("backups_none", "backup_count: 0\n", &[("Todo", &["v0"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        save_as(&mut app, "v1");
+        save_as(&mut app, "v2");
+        assert_eq!(backed_up(&dir), Vec::<String>::new());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2); // Just the database and config.
+    }
+
+    #[test]
+    fn backups_are_kept_in_backup_dir() {
+        let dir = write_test_db("backups_dir", "", &[("Todo", &["v0"]), ("Backlog", &[])]);
+        let backup_dir = dir.join("backups");
+        let config = std::fs::read_to_string(dir.join("config.yml")).unwrap();
+        std::fs::write(dir.join("config.yml"), format!("{config}backup_dir: '{}'\n", backup_dir.display())).unwrap();
+        let mut app = init_app(&dir);
+        save_as(&mut app, "v1");
+        save_as(&mut app, "v2");
+        assert_eq!(backed_up(&backup_dir), ["v1", "v0"]);
+        assert_eq!(backed_up(&dir), Vec::<String>::new());
+    }
+
+    /// Replaces the database written by [`write_test_db`] with one holding a single list, as if edited elsewhere.
+    fn change_db_on_disk(dir: &Path, todos: &[&str]) {
+        let mut state = State::default();
+        state.todo_lists[0].todos = todos.iter().map(|&name| Todo::new(name)).collect();
+        DbFormat::Yaml.storage(&dir.join("db.yml").to_string_lossy()).save(&state).unwrap();
+    }
+
+    #[test]
+    fn databases_changed_on_disk_are_reloaded() {
+        let dir = write_test_db("db_changed_reload", "", &[("Todo", &["a"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::Tick]);
+        assert_eq!(names(&app, 0), ["a"]);
+        assert!(app.message.is_none());
+
+        change_db_on_disk(&dir, &["a", "added elsewhere"]);
+        press(&mut app, &[Action::Tick]);
+        assert_eq!(names(&app, 0), ["a", "added elsewhere"]);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.message.as_ref().unwrap().text, "Reloaded, since the database changed on disk");
+        assert!(!app.needs_saving);
+    }
+
+    #[test]
+    fn databases_changed_on_disk_ask_before_replacing_unsaved_changes() {
+        use Action::*;
+        let dir = write_test_db("db_changed_confirm", "confirm_delete: false\n", &[("Todo", &["a", "b"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[DeleteTodo]);
+        change_db_on_disk(&dir, &["a", "b", "added elsewhere"]);
+        press(&mut app, &[Tick]);
+        assert_eq!((app.mode, app.confirm.clone()), (Mode::Confirm, Some(Confirm::ChangedOnDisk)));
+        assert_eq!(names(&app, 0), ["b"]);
+
+        // Keeping the changes asks no more until the database changes again.
+        press(&mut app, &[ConfirmNo, Tick]);
+        assert_eq!((app.mode, names(&app, 0)), (Mode::Normal, vec!["b"]));
+        assert!(app.needs_saving);
+
+        change_db_on_disk(&dir, &["a", "b", "added again"]);
+        press(&mut app, &[Tick, ConfirmYes]);
+        assert_eq!((app.mode, names(&app, 0)), (Mode::Normal, vec!["a", "b", "added again"]));
+        assert!(!app.needs_saving);
+    }
+
+    #[test]
+    fn idle_ticks_are_only_sent_when_something_changes_with_time() {
+        let dir = write_test_db("idle_ticks", "auto_reload: false\n", &[("Todo", &["a"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        assert_eq!(app.next_timeout(), None);
+        app.config.clock_format = Some("%H:%M".to_string());
+        assert_eq!(app.next_timeout(), Some((Duration::from_millis(app.config.tick_rate_ms), Action::Tick)));
+        app.config.clock_format = None;
+        app.config.auto_reload = true;
+        assert!(matches!(app.next_timeout(), Some((delay, Action::Tick)) if delay <= DB_CHECK_RATE));
+        press(&mut app, &[Action::SetMode(Mode::Insert)]);
+        assert_eq!(app.next_timeout(), None);
+    }
+
+    #[test]
+    fn ui_state_reopens_where_the_user_left_off() {
+        let dir = write_test_db("ui_state_restored", "", &[("Todo", &["a", "b", "c"]), ("Backlog", &["d", "e"])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::MoveRight, Action::MoveDown]);
+        app.save_ui_state();
+        let app = init_app(&dir);
+        assert_eq!(selected(&app), (1, 1));
+    }
+
+    #[test]
+    fn ui_state_beyond_the_board_is_clamped() {
+        let dir = write_test_db("ui_state_clamped", "", &[("Todo", &["a", "b", "c"]), ("Backlog", &["d", "e"])]);
+        std::fs::write(dir.join("db.yml.ui-state"), "todo_list: 1\ntodo: 9\nscroll_offsets: [0, 7]\n").unwrap();
+        let mut app = init_app(&dir);
+        assert_eq!(selected(&app), (1, 1));
+        // Scrolled past its todos, the list is scrolled back to show them.
+        let buffer = draw(&mut app, 40, 10);
+        assert!(row_text(&buffer, 1).contains("• d"), "{}", row_text(&buffer, 1));
+        std::fs::write(dir.join("db.yml.ui-state"), "todo_list: 5\ntodo: 2\n").unwrap();
+        assert_eq!(selected(&init_app(&dir)), (1, 1));
+    }
+
+    #[test]
+    fn corrupt_ui_state_is_ignored() {
+        let dir = write_test_db("ui_state_corrupt", "", &[("Todo", &["a", "b"]), ("Backlog", &[])]);
+        for corrupt in ["todo_list: [\n", "todo: -1\n", "\u{0}\u{1}"] {
+            std::fs::write(dir.join("db.yml.ui-state"), corrupt).unwrap();
+            assert_eq!(selected(&init_app(&dir)), (0, 0));
+        }
+    }
+
+    #[test]
+    fn read_only_sessions_leave_the_ui_state_alone() {
+        let dir = write_test_db("ui_state_read_only", "read_only: true\n", &[("Todo", &["a", "b"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::MoveDown]);
+        app.save_ui_state();
+        assert!(!dir.join("db.yml.ui-state").exists());
+    }
+
+    #[test]
+    fn read_only_sessions_change_nothing_whatever_is_pressed() {
+        use Action::*;
+        let dir = write_test_db("read_only_every_key", "read_only: true\n", &[("Todo", &["a", "b", "c"]), ("Backlog", &["d"])]);
+        let storage = DbFormat::Yaml.storage(&dir.join("db.yml").to_string_lossy());
+        let mut state = storage.load().unwrap();
+        state.todo_lists[0].todos[1].marked = true;
+        state.marks.insert('a', state.todo_lists[0].todos[2].id);
+        storage.save(&state).unwrap();
+        let db = std::fs::read(dir.join("db.yml")).unwrap();
+        let mut app = init_app(&dir);
+        // Todo ids are given out afresh on loading, so reloading changes them but nothing else.
+        let board = |app: &App| {
+            let todo_lists = app.todo_lists.iter().map(|todo_list| {
+                todo_list.todos.iter().map(|todo| (todo.name.clone(), todo.marked)).collect::<Vec<_>>()
+            });
+            let marks = app.marks.iter().map(|(&mark, &id)| (mark, app.find_todo(id)));
+            (todo_lists.collect::<Vec<_>>(), marks.collect::<Vec<_>>())
+        };
+        let before = board(&app);
+
+        let mut mappings: Vec<(KeyPress, Action)> = default_key_mappings().into_iter().collect();
+        mappings.sort_by_key(|(key_press, _)| (key_press.mode.name(), key_press.key_name()));
+        for (key_press, action) in mappings {
+            if matches!(action, YankToClipboard | Export) {
+                continue; // Reach outside the test, and leave the board alone anyway.
+            }
+            app.mode = Mode::Normal;
+            app.quit = false;
+            app.selection = Selection { todo: 1, ..Selection::default() }; // With todos to move past either way.
+            let enter_mode: &[Action] = match key_press.mode {
+                Mode::Visual => &[SetMode(Mode::Visual)],
+                Mode::Finder => &[SetMode(Mode::Finder)],
+                Mode::Popup => &[ShowHelp],
+                _ => &[],
+            };
+            press(&mut app, enter_mode);
+            if app.mode != key_press.mode {
+                continue; // Only reached by changing the board, like insert mode.
+            }
+            press(&mut app, &[action]);
+            if let AwaitRegister(register_action) = action {
+                press(&mut app, &[WithRegister(register_action, 'a'), WithRegister(register_action, 'b')]);
+            }
+            let key = format!("{} in {} mode", key_press.key_name(), key_press.mode.name());
+            assert_eq!(board(&app), before, "{key}");
+            assert!(!app.needs_saving, "{key}");
+            assert!(!matches!(app.mode, Mode::Insert | Mode::Picker | Mode::Confirm), "{key}");
+        }
+        assert_eq!(std::fs::read(dir.join("db.yml")).unwrap(), db);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2); // Just the database and config.
+    }
+
+    #[test]
+    fn sorting_cycles_through_orders_keeping_ties_and_the_selection() {
+        use Action::*;
+        let dir = write_test_db("sort_list", "", &[("Todo", &["b", "A", "c", "a", "B"]), ("Backlog", &["z", "y"])]);
+        let mut app = init_app(&dir);
+        app.todo_lists[0].todos[2].marked = true;
+        app.todo_lists[0].todos[3].marked = true;
+        press(&mut app, &[MoveDown, MoveDown, MoveDown, SortList]);
+        assert_eq!(names(&app, 0), ["A", "a", "b", "B", "c"]);
+        assert_eq!(selected_name(&app), "a");
+        press(&mut app, &[SortList]);
+        assert_eq!(names(&app, 0), ["A", "b", "B", "a", "c"]);
+        assert_eq!(selected_name(&app), "a");
+        press(&mut app, &[SortList]);
+        assert_eq!(names(&app, 0), ["A", "a", "b", "B", "c"]);
+
+        // Undo steps back through each sort, to the order the todos were put in by hand.
+        press(&mut app, &[Undo]);
+        assert_eq!(names(&app, 0), ["A", "b", "B", "a", "c"]);
+        press(&mut app, &[Undo, Undo]);
+        assert_eq!(names(&app, 0), ["b", "A", "c", "a", "B"]);
+        assert_eq!(selected_name(&app), "a");
+    }
+
+    #[test]
+    fn sorting_another_list_starts_the_cycle_over() {
+        use Action::*;
+        let dir = write_test_db("sort_other_list", "", &[("Todo", &["b", "a"]), ("Backlog", &["b", "a"])]);
+        let mut app = init_app(&dir);
+        app.todo_lists[1].todos[1].marked = true;
+        press(&mut app, &[SortList]);
+        app.selection = Selection { todo_list: 1, ..Selection::default() }; // Without a key press in between.
+        press(&mut app, &[SortList]);
+        assert_eq!(names(&app, 1), ["a", "b"]);
+        press(&mut app, &[SortList]);
+        assert_eq!(names(&app, 1), ["b", "a"]);
+        press(&mut app, &[MoveLeft, MoveRight, SortList]);
+        assert_eq!(names(&app, 1), ["a", "b"]);
+    }
+
+    /// Names of the todos in a list of the database on disk written by [`write_test_db`].
+    fn names_on_disk(dir: &Path, todo_list_idx: usize) -> Vec<String> {
+        let state = DbFormat::Yaml.storage(&dir.join("db.yml").to_string_lossy()).load().unwrap();
+        state.todo_lists[todo_list_idx].todos.iter().map(|todo| todo.name.clone()).collect()
+    }
+
+    #[test]
+    fn immediate_autosave_saves_after_each_change_outside_insert_mode() {
+        use Action::*;
+        let dir = write_test_db("autosave_immediate", "autosave: immediate\nconfirm_delete: false\n", &[("Todo", &["a", "b"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[MoveDown, MoveTodoUp]);
+        assert_eq!(names_on_disk(&dir, 0), ["b", "a"]);
+        assert!(!app.needs_saving);
+
+        press(&mut app, &[SetMode(Mode::Insert)]);
+        type_text(&mut app, "!");
+        assert_eq!(names_on_disk(&dir, 0), ["b", "a"]);
+        press(&mut app, &[SetMode(Mode::Normal)]);
+        assert_eq!(names_on_disk(&dir, 0), ["b!", "a"]);
+    }
+
+    #[test]
+    fn debounced_autosave_waits_for_a_pause_outside_insert_and_confirm_modes() {
+        use Action::*;
+        let dir = write_test_db("autosave_debounce", "autosave: {debounce: 5}\nauto_reload: false\n", &[("Todo", &["a", "b"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        assert_eq!(app.next_timeout(), None);
+        press(&mut app, &[MoveDown, MoveTodoUp]);
+        assert_eq!(names_on_disk(&dir, 0), ["a", "b"]);
+        let Some((delay, Autosave)) = app.next_timeout() else { panic!("{:?}", app.next_timeout()) };
+        assert!(delay > Duration::from_secs(4) && delay <= Duration::from_secs(5));
+
+        // Nothing is saved while a question is open or a todo is being edited.
+        press(&mut app, &[DeleteTodo]);
+        assert_eq!(app.mode, Mode::Confirm);
+        assert!(!app.can_autosave());
+        assert_eq!(app.next_timeout(), None);
+        press(&mut app, &[ConfirmNo, SetMode(Mode::Insert)]);
+        assert!(!app.can_autosave());
+        assert_eq!(app.next_timeout(), None);
+
+        press(&mut app, &[SetMode(Mode::Normal)]);
+        assert!(app.can_autosave());
+        app.autosave_from -= Duration::from_secs(5);
+        assert!(matches!(app.next_timeout(), Some((Duration::ZERO, Autosave))));
+        press(&mut app, &[Autosave]);
+        assert_eq!(names_on_disk(&dir, 0), ["b", "a"]);
+        assert!(!app.can_autosave());
+    }
+
+    #[test]
+    fn questions_are_answered_with_y_or_n_alone() {
+        let dir = write_test_db("confirm_delete", "", &[("Todo", &["a", "b", "c"]), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        press_key(&mut app, KeyCode::Char('j'), KeyModifiers::NONE);
+        for answer in [KeyCode::Char('n'), KeyCode::Esc] {
+            press_key(&mut app, KeyCode::Char('d'), KeyModifiers::NONE);
+            assert_eq!(app.mode, Mode::Confirm);
+            press_key(&mut app, answer, KeyModifiers::NONE);
+            assert_eq!(app.mode, Mode::Normal);
+            assert_eq!(names(&app, 0), ["a", "b", "c"]);
+            assert_eq!(selected(&app), (0, 1));
+            assert!(!app.needs_saving);
+        }
+
+        // Keys mapped in other modes do nothing until the question is answered.
+        press_key(&mut app, KeyCode::Char('d'), KeyModifiers::NONE);
+        for c in ['d', 'j', 'm', 'u', 'q', 'i', 'Y', 'x'] {
+            assert_eq!(app.key_action(KeyCode::Char(c), KeyModifiers::NONE), None, "{c}");
+        }
+        press_key(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+        press_key(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(app.mode, Mode::Confirm);
+        assert_eq!(names(&app, 0), ["a", "b", "c"]);
+        assert!(!app.quit);
+
+        press_key(&mut app, KeyCode::Char('y'), KeyModifiers::NONE);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(names(&app, 0), ["a", "c"]);
+        assert_eq!(selected(&app), (0, 1));
+    }
+
+    #[test]
+    fn visual_mode_acts_on_the_whole_range() {
+        use Action::*;
+        let dir = write_test_db("visual_ranges", "confirm_delete: false\n", &[("Todo", &["a", "b", "c", "d"]), ("Backlog", &["x", "y"])]);
+        let mut app = init_app(&dir);
+        let marked = |app: &App| -> Vec<bool> { app.todo_lists[0].todos.iter().map(|todo| todo.marked).collect() };
+        press(&mut app, &[MoveDown, SetMode(Mode::Visual), MoveDown, ToggleMark]);
+        assert_eq!(marked(&app), [false, true, true, false]);
+        assert_eq!(app.mode, Mode::Normal);
+        // Ranges can be selected upwards too, and toggle back.
+        press(&mut app, &[SetMode(Mode::Visual), MoveUp, ToggleMark]);
+        assert_eq!(marked(&app), [false, false, false, false]);
+        press(&mut app, &[Undo]);
+        assert_eq!(marked(&app), [false, true, true, false]);
+
+        press(&mut app, &[MoveTop, SetMode(Mode::Visual), MoveBottom, MoveUp, DeleteTodo]);
+        assert_eq!(names(&app, 0), ["d"]);
+        assert_eq!(names(&app, 1), ["x", "y", "b", "c"]); // Marked todos go to the backlog.
+        press(&mut app, &[Undo]);
+        assert_eq!((names(&app, 0), names(&app, 1)), (vec!["a", "b", "c", "d"], vec!["x", "y"]));
+    }
+
+    #[test]
+    fn visual_mode_moves_the_range_between_lists_as_a_block() {
+        use Action::*;
+        let dir = write_test_db("visual_moves", "", &[("Todo", &["a", "b", "c", "d"]), ("Backlog", &["x", "y"])]);
+        let mut app = init_app(&dir);
+        press(&mut app, &[MoveDown, SetMode(Mode::Visual), MoveDown, MoveDown, MoveTodoRight]);
+        assert_eq!(names(&app, 0), ["a"]);
+        assert_eq!(names(&app, 1), ["x", "b", "c", "d", "y"]);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(selected(&app), (1, 1));
+
+        press(&mut app, &[SetMode(Mode::Visual), MoveUp, MoveTodoLeft]);
+        assert_eq!(names(&app, 0), ["x", "b", "a"]);
+        assert_eq!(names(&app, 1), ["c", "d", "y"]);
+
+        // Each move is undone in one step.
+        press(&mut app, &[Undo]);
+        assert_eq!((names(&app, 0), names(&app, 1)), (vec!["a"], vec!["x", "b", "c", "d", "y"]));
+        press(&mut app, &[Undo]);
+        assert_eq!((names(&app, 0), names(&app, 1)), (vec!["a", "b", "c", "d"], vec!["x", "y"]));
+    }
+
+    #[test]
+    fn selection_is_scrolled_into_view() {
+        use Action::*;
+        let todos: Vec<String> = (1..=30).map(|n| format!("t{n:02}")).collect();
+        let todos: Vec<&str> = todos.iter().map(String::as_str).collect();
+        let dir = write_test_db("scroll_into_view", "", &[("Todo", &todos), ("Backlog", &[])]);
+        let mut app = init_app(&dir);
+        // Todos drawn in the first list, top to bottom, in a terminal with room for ten.
+        let shown = |app: &mut App| -> Vec<String> {
+            let buffer = draw(app, 40, 13);
+            (0..buffer.area.height)
+                .filter_map(|y| row_text(&buffer, y).split_whitespace().find(|word| todos.contains(word)).map(str::to_string))
+                .collect()
+        };
+        assert_eq!(shown(&mut app), todos[..10]);
+        press(&mut app, &[MoveBottom]);
+        assert_eq!(shown(&mut app), todos[20..]);
+        press(&mut app, &[MoveTop]);
+        assert_eq!(shown(&mut app), todos[..10]);
+        press(&mut app, &[MoveDownHalf, MoveDownHalf]);
+        assert_eq!(selected_name(&app), "t11");
+        assert_eq!(shown(&mut app), todos[1..11]);
+        press(&mut app, &[MoveDownHalf]);
+        assert_eq!(shown(&mut app), todos[6..16]);
+        press(&mut app, &[MoveUp; 10]);
+        assert_eq!(shown(&mut app), todos[5..15]);
+
+        // Losing todos from the end, like to a reload, scrolls back up rather than leave rows blank.
+        press(&mut app, &[MoveBottom]);
+        assert_eq!(shown(&mut app), todos[20..]);
+        press(&mut app, &[MoveUp; 6]);
+        assert_eq!(shown(&mut app), todos[20..]);
+        app.todo_lists[0].todos.truncate(25);
+        assert_eq!(shown(&mut app), todos[15..25]);
+        assert_eq!(selected_name(&app), "t24");
+        app.todo_lists[0].todos.truncate(8);
+        app.clamp_selection();
+        assert_eq!(shown(&mut app), todos[..8]);
+        assert_eq!(app.scroll_offsets[0], 0);
+    }
+
+    /// Keys the fuzz test presses: every kind of character, and the keys bound to actions.
+    const FUZZ_KEYS: &[KeyCode] = &[
+        KeyCode::Esc, KeyCode::Enter, KeyCode::Backspace, KeyCode::Delete, KeyCode::Tab, KeyCode::BackTab,
+        KeyCode::Left, KeyCode::Right, KeyCode::Up, KeyCode::Down, KeyCode::Home, KeyCode::End,
+        KeyCode::PageUp, KeyCode::PageDown, KeyCode::Char(' '), KeyCode::Char('é'), KeyCode::Char('\u{301}'),
+        KeyCode::Char('日'), KeyCode::Char('👩'), KeyCode::Char('\u{200d}'), KeyCode::Char('🇫'),
+    ];
+
+    /// Random action, made the way [`App::run`] makes them: from key presses, clicks, wheel turns and timeouts.
+    /// Actions reaching outside the test, to the clipboard or arbitrary files, or ending the app are left out.
+    fn fuzz_action(app: &App, rng: &mut Rng, (width, height): (u16, u16)) -> Action {
+        let column = rng.below(width as usize + 2) as u16;
+        let row = rng.below(height as usize + 2) as u16;
+        match rng.below(20) {
+            0 => return Action::Click { column, row },
+            1 => {
+                let directions = [WheelDirection::Up, WheelDirection::Down, WheelDirection::Left, WheelDirection::Right];
+                return Action::Wheel { column, row, direction: directions[rng.below(4)] };
+            }
+            2 => return [Action::Tick, Action::Nop, Action::ShowHints, Action::ExpireMessage, Action::Autosave][rng.below(5)],
+            _ => {}
+        }
+        let code = match rng.below(3) {
+            0 => FUZZ_KEYS[rng.below(FUZZ_KEYS.len())],
+            1 => KeyCode::Char(char::from(b' ' + rng.below(95) as u8)),
+            _ => KeyCode::Char(rng.char()),
+        };
+        let modifiers = [KeyModifiers::NONE, KeyModifiers::NONE, KeyModifiers::SHIFT, KeyModifiers::CONTROL, KeyModifiers::ALT];
+        match app.key_action(code, modifiers[rng.below(modifiers.len())]) {
+            Some(Action::Quit | Action::ForceQuit | Action::Terminate | Action::Export | Action::Import
+                | Action::PasteClipboard | Action::YankToClipboard) | None => Action::Nop,
+            Some(action) => action,
+        }
+    }
+
+    #[test]
+    fn random_input_never_panics() {
+        let todos: &[(&str, &[&str])] = &[
+            ("Todo", &["one", "two 👩\u{200d}💻 pair with Ana", "e\u{301}tude", "a long todo that will not fit in a narrow list at all"]),
+            ("Doing", &["日本語", ""]),
+            ("Done", &[]),
+        ];
+        for seed in 1..=4u64 {
+            let dir = write_test_db(&format!("fuzz_{seed}"), "", todos);
+            let mut app = init_app(&dir);
+            let mut rng = Rng(seed.wrapping_mul(0x9e3779b97f4a7c15));
+            let mut size = (80, 24);
+            let mut terminal = Terminal::new(TestBackend::new(size.0, size.1)).unwrap();
+            for step in 0..3000 {
+                if rng.below(100) == 0 {
+                    size = (rng.below(120) as u16, rng.below(40) as u16);
+                    terminal.backend_mut().resize(size.0, size.1);
+                }
+                let action = fuzz_action(&app, &mut rng, size);
+                // Drawing is slow, so only some steps are drawn, and always those the mouse needs the layout for.
+                if matches!(action, Action::Click { .. } | Action::Wheel { .. }) || rng.below(16) == 0 {
+                    terminal.draw(|frame| app.render(frame)).unwrap();
+                }
+                if let Err(err) = app.update(action) {
+                    panic!("seed {seed}, step {step}: {action:?} failed: {err:#}");
+                }
+            }
+            terminal.draw(|frame| app.render(frame)).unwrap();
+        }
+    }
+
+    fn write_corrupt_db(name: &str, contents: &str) -> PathBuf {
+        let dir = write_test_db(name, "", &[]);
+        std::fs::write(dir.join("db.yml"), contents).unwrap();
+        dir
+    }
+
+    /// Checks the app started on a new database, saying where the corrupt one given was moved to.
+    fn assert_corrupt_db_moved_aside(app: &App, dir: &Path, contents: &str) {
+        let message = app.message.as_ref().unwrap();
+        assert_eq!(message.level, MessageLevel::Error, "{contents:?}");
+        assert!(message.text.starts_with("Database was moved to "), "{contents:?}");
+        assert!(app.needs_saving);
+        assert_eq!(app.todo_lists, State::default().todo_lists);
+        let moved: Vec<_> = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|file_name| file_name.starts_with("db.yml.corrupt-"))
+            .collect();
+        assert_eq!(moved.len(), 1, "{contents:?}");
+        assert_eq!(std::fs::read_to_string(dir.join(&moved[0])).unwrap(), contents);
+    }
+
+    #[test]
+    fn corrupt_databases_are_moved_aside() {
+        for (name, contents) in [
+            ("corrupt_malformed", "todo_lists:\n- name: Todo\n  todos: [\n"),
+            ("corrupt_wrong_shape", "version: '0.2'\ntodo_lists: 3\n"),
+            ("corrupt_empty", ""),
+        ] {
+            let dir = write_corrupt_db(name, contents);
+            let app = init_app(&dir);
+            assert_corrupt_db_moved_aside(&app, &dir, contents);
+            assert_eq!(app.mode, Mode::Popup, "{contents:?}");
+            assert_eq!(app.popup.title, "Database could not be read");
+        }
+    }
+
+    #[test]
+    fn corrupt_database_popup_waits_for_the_insert_scratch_question() {
+        let dir = write_corrupt_db("corrupt_with_scratch", "todo_lists: [\n");
+        write_insert_scratch(&dir, &InsertScratch {
+            todo_list: 0,
+            todo: 0,
+            original: String::new(),
+            name: "Call Ana".to_string(),
+            char: 8,
+        });
+        let mut app = init_app(&dir);
+        assert_corrupt_db_moved_aside(&app, &dir, "todo_lists: [\n");
+        assert_eq!(app.mode, Mode::Confirm);
+        press(&mut app, &[Action::ConfirmYes]);
+        assert_eq!((app.mode, app.popup.title.as_str()), (Mode::Popup, "Database could not be read"));
+        assert_eq!(names(&app, 0), ["Call Ana"]);
+    }
+
+    fn write_insert_scratch(dir: &Path, scratch: &InsertScratch) {
+        std::fs::write(dir.join("db.yml.insert"), serde_yaml::to_string(scratch).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn insert_scratch_is_offered_back_into_its_todo() {
+        let dir = write_test_db("scratch_restore", "", &[("Todo", &["Buy milk", "Call Ana"]), ("Backlog", &[])]);
+        write_insert_scratch(&dir, &InsertScratch {
+            todo_list: 0,
+            todo: 1,
+            original: "Call Ana".to_string(),
+            name: "Call Ana about the trip".to_string(),
+            char: 22,
+        });
+        let mut app = init_app(&dir);
+        assert_eq!(app.mode, Mode::Confirm);
+        assert!(matches!(&app.confirm, Some(Confirm::RestoreInsert(scratch)) if scratch.name == "Call Ana about the trip"));
+
+        press(&mut app, &[Action::ConfirmYes]);
+        assert_eq!(names(&app, 0), ["Buy milk", "Call Ana about the trip"]);
+        assert_eq!((app.selection.todo_list, app.selection.todo), (0, 1));
+        assert_eq!(app.last_insert.map(|last| last.char), Some(22));
+        assert!(!dir.join("db.yml.insert").exists());
+        assert!(app.needs_saving);
+    }
+
+    #[test]
+    fn insert_scratch_of_a_changed_todo_is_restored_as_a_new_todo() {
+        let dir = write_test_db("scratch_new_todo", "", &[("Todo", &["Buy milk"]), ("Backlog", &[])]);
+        write_insert_scratch(&dir, &InsertScratch {
+            todo_list: 0,
+            todo: 0,
+            original: "Renamed since".to_string(),
+            name: "Half typed".to_string(),
+            char: 10,
+        });
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ConfirmYes]);
+        assert_eq!(names(&app, 0), ["Half typed", "Buy milk"]);
+        press(&mut app, &[Action::Undo]);
+        assert_eq!(names(&app, 0), ["Buy milk"]);
+    }
+
+    #[test]
+    fn insert_scratch_declined_is_discarded() {
+        let dir = write_test_db("scratch_decline", "", &[("Todo", &["Buy milk"]), ("Backlog", &[])]);
+        write_insert_scratch(&dir, &InsertScratch {
+            todo_list: 0,
+            todo: 0,
+            original: "Buy milk".to_string(),
+            name: "Buy milk and eggs".to_string(),
+            char: 17,
+        });
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::ConfirmNo]);
+        assert_eq!(names(&app, 0), ["Buy milk"]);
+        assert!(!dir.join("db.yml.insert").exists());
+        assert!(init_app(&dir).confirm.is_none());
+    }
+
+    #[test]
+    fn insert_scratch_is_written_once_typing_pauses() {
+        let dir = write_test_db("scratch_debounce", "", &[("Todo", &["Buy"]), ("Backlog", &[])]);
+        let scratch_path = dir.join("db.yml.insert");
+        let mut app = init_app(&dir);
+        press(&mut app, &[Action::SetMode(Mode::Insert)]);
+        type_text(&mut app, " milk");
+        assert!(!scratch_path.exists());
+        assert!(matches!(app.next_timeout(), Some((delay, Action::Tick)) if delay <= INSERT_SCRATCH_DELAY));
+
+        app.insert_scratch_due = Some(Instant::now());
+        press(&mut app, &[Action::Tick]);
+        let scratch: InsertScratch = serde_yaml::from_str(&std::fs::read_to_string(&scratch_path).unwrap()).unwrap();
+        assert_eq!((scratch.original.as_str(), scratch.name.as_str(), scratch.char), ("Buy", "Buy milk", 8));
+        assert_eq!(app.insert_scratch_due, None);
+
+        type_text(&mut app, "!");
+        press(&mut app, &[Action::SetMode(Mode::Normal)]);
+        assert!(!scratch_path.exists());
+        assert_eq!(app.insert_scratch_due, None);
+    }
 }
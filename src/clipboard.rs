@@ -0,0 +1,38 @@
+/// Reads text from the system clipboard.
+#[cfg(feature = "clipboard")]
+pub(crate) fn get_clipboard_text() -> anyhow::Result<String> {
+    with_clipboard(|clipboard| Ok(clipboard.get_text()?))
+}
+
+/// Puts text on the system clipboard.
+#[cfg(feature = "clipboard")]
+pub(crate) fn set_clipboard_text(text: String) -> anyhow::Result<()> {
+    with_clipboard(|clipboard| Ok(clipboard.set_text(text)?))
+}
+
+/// Runs a function with a clipboard handle, opened on first use.
+/// The handle is kept open since on X11 copied text is only served while it lives.
+#[cfg(feature = "clipboard")]
+fn with_clipboard<T>(f: impl FnOnce(&mut arboard::Clipboard) -> anyhow::Result<T>) -> anyhow::Result<T> {
+    use std::cell::RefCell;
+    thread_local! {
+        static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+    }
+    CLIPBOARD.with_borrow_mut(|clipboard| {
+        let clipboard = match clipboard {
+            Some(clipboard) => clipboard,
+            None => clipboard.insert(arboard::Clipboard::new()?),
+        };
+        f(clipboard)
+    })
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub(crate) fn get_clipboard_text() -> anyhow::Result<String> {
+    anyhow::bail!("tdi was built without clipboard support")
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub(crate) fn set_clipboard_text(_text: String) -> anyhow::Result<()> {
+    anyhow::bail!("tdi was built without clipboard support")
+}
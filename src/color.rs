@@ -1,6 +1,8 @@
 use crossterm::style::Color;
 
 pub const FG_MARKED: Color = Color::Red;
+pub const FG_WARNING: Color = Color::Yellow;
+pub const FG_ERROR: Color = Color::Red;
 pub const BG_UNSELECTED: Color = Color::Black;
 pub const FG_UNSELECTED: Color = Color::White;
 pub const BG_SELECTED: Color = Color::White;
@@ -0,0 +1,298 @@
+use crate::{Todo, TodoList};
+use anyhow::bail;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Format the board is exported in, for reading and sharing outside tdi.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum ExportFormat {
+    Markdown,
+    Csv,
+}
+
+impl ExportFormat {
+    /// Format going by a path's extension: Markdown for .md and .markdown, and CSV for .csv.
+    /// Other extensions are refused, rather than guessed at.
+    pub fn for_path(path: &str) -> anyhow::Result<Self> {
+        let extension = Path::new(path).extension().map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("md" | "markdown") => Ok(Self::Markdown),
+            Some("csv") => Ok(Self::Csv),
+            _ => bail!("Don't know how to export to {path}. Use a path ending in .md or .csv"),
+        }
+    }
+
+    /// The board written out in this format.
+    pub fn export(self, todo_lists: &[TodoList]) -> String {
+        match self {
+            Self::Markdown => to_markdown(todo_lists),
+            Self::Csv => to_csv(todo_lists),
+        }
+    }
+}
+
+/// The board as Markdown: each list a `##` heading, followed by its todos in order as task items,
+/// checked if marked. Lists are separated by blank lines.
+pub(crate) fn to_markdown(todo_lists: &[TodoList]) -> String {
+    let mut markdown = String::new();
+    for todo_list in todo_lists {
+        if !markdown.is_empty() {
+            markdown.push('\n');
+        }
+        let _ = writeln!(markdown, "## {}", one_line(&todo_list.name));
+        if !todo_list.todos.is_empty() {
+            markdown.push('\n');
+        }
+        for todo in &todo_list.todos {
+            let check = match todo.marked {
+                true => 'x',
+                false => ' ',
+            };
+            let _ = writeln!(markdown, "- [{check}] {}", one_line(&todo.name));
+        }
+    }
+    markdown
+}
+
+/// The board as CSV, for spreadsheets: a header row, then a row per todo, list by list in order.
+/// The columns are always, in order:
+/// - list: name of the todo's list
+/// - name: name of the todo
+/// - marked: true or false
+pub(crate) fn to_csv(todo_lists: &[TodoList]) -> String {
+    let mut csv = String::from("list,name,marked\n");
+    for todo_list in todo_lists {
+        for todo in &todo_list.todos {
+            let _ = writeln!(csv, "{},{},{}", csv_field(&todo_list.name), csv_field(&todo.name), todo.marked);
+        }
+    }
+    csv
+}
+
+/// Field of a CSV row, quoted if it has commas, quotes or line breaks, with quotes inside doubled.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+/// Outcome of adding todos from Markdown to a board.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub(crate) struct MarkdownImport {
+    pub added: usize,
+    pub skipped: usize,     // Todos left out, since their list already had one of the same name.
+}
+
+impl MarkdownImport {
+    /// What was imported from `source`, to show or print.
+    pub fn summary(&self, source: &str) -> String {
+        match self.skipped {
+            0 => format!("Imported {} todo(s) from {source}", self.added),
+            skipped => format!("Imported {} todo(s) from {source}, skipping {skipped} already there", self.added),
+        }
+    }
+}
+
+/// Adds the task items of some Markdown, like the board exported by [`to_markdown`], to the end of the lists named
+/// by the `##` headings they are under, creating lists that don't exist yet. Items before any heading go to the list at
+/// `default_list`. Checked items are added marked, and nested items as todos of their own.
+/// Unless `keep_duplicates`, items whose list already has a todo of the same name are skipped.
+pub(crate) fn add_markdown_todos(
+    todo_lists: &mut Vec<TodoList>,
+    markdown: &str,
+    default_list: usize,
+    keep_duplicates: bool,
+) -> MarkdownImport {
+    let mut import = MarkdownImport::default();
+    let mut heading = None;
+    for line in markdown.lines() {
+        if let Some(name) = line.strip_prefix("## ") {
+            heading = Some(name.trim());
+            continue;
+        }
+        let Some(todo) = parse_task_item(line) else { continue };
+        let todo_list_idx = match heading {
+            Some(name) => list_named(todo_lists, name),
+            None if default_list < todo_lists.len() => default_list,
+            None => list_named(todo_lists, "Todo"), // Board without lists
+        };
+        let todos = &mut todo_lists[todo_list_idx].todos;
+        if !keep_duplicates && todos.iter().any(|existing| existing.name == todo.name) {
+            import.skipped += 1;
+            continue;
+        }
+        todos.push(todo);
+        import.added += 1;
+    }
+    import
+}
+
+/// Index of the list with a name, which is added to the end of the board if there is none.
+fn list_named(todo_lists: &mut Vec<TodoList>, name: &str) -> usize {
+    if let Some(todo_list_idx) = todo_lists.iter().position(|todo_list| todo_list.name == name) {
+        return todo_list_idx;
+    }
+    todo_lists.push(TodoList {
+        name: name.to_string(),
+        todos: vec![],
+    });
+    todo_lists.len() - 1
+}
+
+/// Todo from a task item like `- [x] name`, at any indentation. None for other lines, and items without a name.
+fn parse_task_item(line: &str) -> Option<Todo> {
+    let item = line.trim_start().strip_prefix(['-', '*', '+'])?.strip_prefix(' ')?.trim_start();
+    let (marked, name) = match item.get(..3)? {
+        "[ ]" => (false, &item[3..]),
+        "[x]" | "[X]" => (true, &item[3..]),
+        _ => return None,
+    };
+    let name: String = name.trim().chars().filter(|c| !c.is_control()).collect();
+    if name.is_empty() {
+        return None;
+    }
+    let mut todo = Todo::new(name);
+    todo.marked = marked;
+    Some(todo)
+}
+
+/// Text with line breaks turned into spaces, so it stays a single heading or task item.
+fn one_line(text: &str) -> String {
+    text.lines().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Board the golden files in tests/fixtures were exported from.
+    fn board() -> Vec<TodoList> {
+        let todo = |name: &str, marked: bool| Todo { marked, ..Todo::new(name) };
+        vec![
+            TodoList {
+                name: "Todo".to_string(),
+                todos: vec![
+                    todo("Buy milk", false),
+                    todo("Call Ana, about the trip", true),
+                    todo("Pack \"everything\"\nand the charger", false),
+                ],
+            },
+            TodoList { name: "Doing".to_string(), todos: vec![] },
+            TodoList {
+                name: "Done, mostly".to_string(),
+                todos: vec![todo("Write the café's menu 日本語", true), todo("- [ ] not a nested item", false)],
+            },
+        ]
+    }
+
+    #[test]
+    fn markdown_export_matches_the_golden_file() {
+        assert_eq!(to_markdown(&board()), include_str!("../tests/fixtures/export.md"));
+        assert_eq!(ExportFormat::for_path("board.MD").unwrap().export(&board()), to_markdown(&board()));
+    }
+
+    fn names(todo_list: &TodoList) -> Vec<(&str, bool)> {
+        todo_list.todos.iter().map(|todo| (todo.name.as_str(), todo.marked)).collect()
+    }
+
+    #[test]
+    fn markdown_import_adds_items_under_headings_to_their_lists() {
+        let mut todo_lists = board();
+        let markdown = "\
+            - [ ] Before any heading\n\
+            ## Doing\n\
+            - [ ] Write tests\n\
+            * [X] Fix the build\n\
+            + [x] Deploy\n\
+            ## New list\n\
+            Some prose, skipped\n\
+            - plain item, skipped\n\
+            - [ ]\n\
+            - [ ] Parent\n\
+            \x20   - [ ] Nested child\n\
+            \t- [x] Tabbed child\n";
+        let import = add_markdown_todos(&mut todo_lists, markdown, 2, false);
+        assert_eq!(import, MarkdownImport { added: 7, skipped: 0 });
+        assert_eq!(names(&todo_lists[1]), [("Write tests", false), ("Fix the build", true), ("Deploy", true)]);
+        assert_eq!(names(&todo_lists[2]).last(), Some(&("Before any heading", false)));
+        assert_eq!(todo_lists[3].name, "New list");
+        assert_eq!(names(&todo_lists[3]), [("Parent", false), ("Nested child", false), ("Tabbed child", true)]);
+    }
+
+    #[test]
+    fn markdown_import_skips_duplicates_unless_kept() {
+        let markdown = "## Todo\n- [ ] Buy milk\n- [ ] Buy milk\n- [ ] Buy bread\n";
+        let mut todo_lists = board();
+        let import = add_markdown_todos(&mut todo_lists, markdown, 0, false);
+        assert_eq!(import, MarkdownImport { added: 1, skipped: 2 });
+        assert_eq!(todo_lists[0].todos.len(), 4);
+        assert_eq!(import.summary("todo.md"), "Imported 1 todo(s) from todo.md, skipping 2 already there");
+
+        let mut todo_lists = board();
+        let import = add_markdown_todos(&mut todo_lists, markdown, 0, true);
+        assert_eq!(import, MarkdownImport { added: 3, skipped: 0 });
+        assert_eq!(todo_lists[0].todos.len(), 6);
+        assert_eq!(import.summary("todo.md"), "Imported 3 todo(s) from todo.md");
+    }
+
+    #[test]
+    fn markdown_import_into_an_empty_board_makes_a_list() {
+        let mut todo_lists = vec![];
+        add_markdown_todos(&mut todo_lists, "- [ ] Orphan\n", 0, false);
+        assert_eq!(todo_lists[0].name, "Todo");
+        assert_eq!(names(&todo_lists[0]), [("Orphan", false)]);
+    }
+
+    #[test]
+    fn markdown_export_imports_back_as_the_same_board() {
+        let mut todo_lists: Vec<TodoList> = board()
+            .into_iter()
+            .map(|todo_list| TodoList { todos: vec![], ..todo_list })
+            .collect();
+        add_markdown_todos(&mut todo_lists, &to_markdown(&board()), 0, false);
+        let mut expected = board();
+        expected[0].todos[2].name = "Pack \"everything\" and the charger".to_string();
+        assert_eq!(todo_lists, expected);
+    }
+
+    #[test]
+    fn csv_export_matches_the_golden_file() {
+        assert_eq!(to_csv(&board()), include_str!("../tests/fixtures/export.csv"));
+        assert_eq!(ExportFormat::for_path("board.csv").unwrap().export(&board()), to_csv(&board()));
+    }
+
+    #[test]
+    fn csv_columns_are_list_name_and_marked() {
+        let csv = to_csv(&board());
+        let mut rows = csv.lines();
+        assert_eq!(rows.next(), Some("list,name,marked"));
+        assert_eq!(rows.next(), Some("Todo,Buy milk,false"));
+        assert_eq!(to_csv(&[]), "list,name,marked\n");
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("carriage\rreturn"), "\"carriage\rreturn\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn export_format_goes_by_extension() {
+        assert_eq!(ExportFormat::for_path("a.md").unwrap(), ExportFormat::Markdown);
+        assert_eq!(ExportFormat::for_path("a.Markdown").unwrap(), ExportFormat::Markdown);
+        assert_eq!(ExportFormat::for_path("a.CSV").unwrap(), ExportFormat::Csv);
+        for path in ["a.txt", "a", "md"] {
+            assert!(ExportFormat::for_path(path).unwrap_err().to_string().starts_with("Don't know how to export"));
+        }
+    }
+
+    #[test]
+    fn markdown_export_of_an_empty_board_is_empty() {
+        assert_eq!(to_markdown(&[]), "");
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{Theme, TodoList};
+use ratatui::Frame;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear};
+use unicode_width::UnicodeWidthStr;
+
+/// Fuzzy finder overlay, listing every todo across all lists.
+#[derive(Clone, Eq, PartialEq, Default, Debug)]
+pub(crate) struct Finder {
+    pub query: String,
+    candidates: Vec<Candidate>,     // Every todo on the board.
+    matches: Vec<usize>,            // Indices of candidates matching the query, best first.
+    selected: usize,                // Index into matches.
+}
+
+impl Finder {
+
+    /// Creates a finder whose candidates are all todos in the lists supplied.
+    pub fn new(todo_lists: &[TodoList]) -> Self {
+        let candidates = todo_lists
+            .iter()
+            .enumerate()
+            .flat_map(|(todo_list_idx, todo_list)| {
+                todo_list.todos.iter().enumerate().map(move |(todo_idx, todo)| Candidate {
+                    todo_list: todo_list_idx,
+                    todo: todo_idx,
+                    label: format!("[{}] {}", todo_list.name, todo.name),
+                })
+            })
+            .collect();
+        let mut finder = Self {
+            query: String::new(),
+            candidates,
+            matches: vec![],
+            selected: 0,
+        };
+        finder.update_matches();
+        finder
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.update_matches();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.update_matches();
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Indices of the todo currently selected in the finder, if any.
+    pub fn selected_todo(&self) -> Option<(usize, usize)> {
+        let candidate_idx = *self.matches.get(self.selected)?;
+        let candidate = &self.candidates[candidate_idx];
+        Some((candidate.todo_list, candidate.todo))
+    }
+
+    /// Rescores all candidates against the current query.
+    fn update_matches(&mut self) {
+        let query: Vec<char> = self.query.chars().flat_map(char::to_lowercase).collect();
+        let mut scored: Vec<(i64, usize)> = self.candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, candidate)| fuzzy_score(&query, &candidate.label).map(|score| (score, i)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score)); // Stable, so ties keep board order.
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    pub fn render(&self, theme: &Theme, area: Rect, frame: &mut Frame) {
+        let width = (area.width * 3 / 5).max(20).min(area.width);
+        let height = (area.height * 3 / 5).max(5).min(area.height);
+        let popup_area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let block = Block::default()
+            .title("Find")
+            .borders(Borders::all())
+            .title_alignment(Alignment::Center)
+            .fg(theme.border_selected);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(block, popup_area);
+        if popup_area.width < 4 || popup_area.height < 3 {
+            return;
+        }
+
+        // Query
+        let mut line_area = Rect {
+            x: popup_area.x + 2,
+            y: popup_area.y + 1,
+            width: popup_area.width - 4,
+            height: 1,
+        };
+        let query_line = format!("> {}", self.query);
+        frame.render_widget(Line::from(query_line), line_area);
+        let cursor_x = line_area.x + 2 + self.query.width() as u16;
+        frame.set_cursor_position((cursor_x.min(line_area.right()), line_area.y));
+
+        // Candidates, scrolled so the selected one stays visible
+        let visible = popup_area.height.saturating_sub(3) as usize;
+        let offset = (self.selected + 1).saturating_sub(visible);
+        for (i, candidate_idx) in self.matches.iter().enumerate().skip(offset).take(visible) {
+            let candidate = &self.candidates[*candidate_idx];
+            let (bg_color, fg_color) = match i == self.selected {
+                false => (theme.bg_unselected, theme.fg_unselected),
+                true => (theme.bg_selected, theme.fg_selected),
+            };
+            line_area.y += 1;
+            let candidate_line = Line::from(candidate.label.as_str()).bg(bg_color).fg(fg_color);
+            frame.render_widget(candidate_line, line_area);
+        }
+    }
+}
+
+/// A todo that can be jumped to from the [`Finder`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Candidate {
+    todo_list: usize,
+    todo: usize,
+    label: String,
+}
+
+/// Scores how well a lowercased query matches a candidate as an in-order subsequence.
+/// Consecutive matches and matches at the start of words score higher, gaps score lower.
+/// Returns None if the candidate does not contain every character of the query.
+fn fuzzy_score(query: &[char], candidate: &str) -> Option<i64> {
+    let mut query_chars = query.iter().peekable();
+    let mut score = 0;
+    let mut prev_matched = false;
+    let mut prev_char = ' ';
+    for c in candidate.chars() {
+        let Some(&&query_char) = query_chars.peek() else { break };
+        let matched = c.to_lowercase().eq(std::iter::once(query_char));
+        if matched {
+            score += 1;
+            if prev_matched {
+                score += 5;
+            }
+            if !prev_char.is_alphanumeric() {
+                score += 3;
+            }
+            query_chars.next();
+        }
+        else {
+            score -= 1;
+        }
+        prev_matched = matched;
+        prev_char = c;
+    }
+    match query_chars.peek() {
+        Some(_) => None,
+        None => Some(score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Todo;
+
+    fn score(query: &str, candidate: &str) -> Option<i64> {
+        fuzzy_score(&query.chars().collect::<Vec<_>>(), candidate)
+    }
+
+    /// Finder over lists of todos by name, with `query` typed into it.
+    fn finder(todo_lists: &[(&str, &[&str])], query: &str) -> Finder {
+        let todo_lists: Vec<TodoList> = todo_lists
+            .iter()
+            .map(|(name, todos)| TodoList {
+                name: name.to_string(),
+                todos: todos.iter().map(|&name| Todo::new(name)).collect(),
+            })
+            .collect();
+        let mut finder = Finder::new(&todo_lists);
+        query.chars().for_each(|c| finder.push(c));
+        finder
+    }
+
+    /// Names of the todos matching, best first.
+    fn matches(finder: &Finder) -> Vec<&str> {
+        finder.matches.iter().map(|&i| finder.candidates[i].label.as_str()).collect()
+    }
+
+    #[test]
+    fn queries_match_as_in_order_subsequences() {
+        assert!(score("", "anything").is_some());
+        assert!(score("abc", "abc").is_some());
+        assert!(score("abc", "a big cat").is_some());
+        assert!(score("abc", "cba").is_none());
+        assert!(score("abc", "ab").is_none());
+        assert!(score("aa", "a").is_none());
+        assert!(score("a", "").is_none());
+    }
+
+    #[test]
+    fn candidates_match_whatever_their_case() {
+        assert_eq!(score("buy", "BUY milk"), score("buy", "buy milk"));
+        let found = finder(&[("Todo", &["Buy milk", "Walk dog"])], "BUY");
+        assert_eq!(matches(&found), ["[Todo] Buy milk"]);
+    }
+
+    #[test]
+    fn consecutive_matches_and_word_starts_score_higher() {
+        assert!(score("ab", "ab") > score("ab", "axb"));
+        assert!(score("b", "a b") > score("b", "ab"));
+        assert!(score("b", "a-b") > score("b", "ab"));
+        assert!(score("ab", "xab") > score("ab", "xxa b"));
+        assert!(score("ab", "ab") > score("ab", "xab"));
+    }
+
+    #[test]
+    fn best_matches_come_first_and_ties_keep_board_order() {
+        let found = finder(&[("Todo", &["red tent", "pay rent"]), ("Backlog", &["rent car", "paint"])], "rent");
+        assert_eq!(matches(&found), ["[Backlog] rent car", "[Todo] pay rent", "[Todo] red tent"]);
+        let found = finder(&[("One", &["b", "x"]), ("Two", &["x", "b"])], "x");
+        assert_eq!(matches(&found), ["[One] x", "[Two] x"]);
+    }
+
+    #[test]
+    fn selected_todo_is_among_the_matches() {
+        let mut finder = finder(&[("Todo", &["walk dog", "buy milk"]), ("Backlog", &["fix bike", "buy bread"])], "");
+        assert_eq!(finder.selected_todo(), Some((0, 0)));
+        "buy".chars().for_each(|c| finder.push(c));
+        assert_eq!(finder.selected_todo(), Some((0, 1)));
+        finder.select_next();
+        assert_eq!(finder.selected_todo(), Some((1, 1)));
+        finder.select_next();
+        assert_eq!(finder.selected_todo(), Some((1, 1)));
+        // Typing starts again from the best match.
+        finder.push('b');
+        assert_eq!(finder.selected_todo(), Some((1, 1)));
+        finder.select_prev();
+        assert_eq!(finder.selected_todo(), Some((1, 1)));
+        finder.pop();
+        assert_eq!(finder.selected_todo(), Some((0, 1)));
+        finder.push('z');
+        assert_eq!(finder.selected_todo(), None);
+    }
+}
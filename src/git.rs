@@ -0,0 +1,169 @@
+use anyhow::{Context, anyhow, bail};
+use chrono::Local;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread::JoinHandle;
+
+/// Commits being made in the background, so saving never waits on git.
+struct Committer {
+    running: Option<JoinHandle<anyhow::Result<()>>>,
+    queued: Option<PathBuf>,    // Database saved again while committing, committed once the running commit is done.
+}
+
+thread_local! {
+    static COMMITTER: RefCell<Committer> = const { RefCell::new(Committer { running: None, queued: None }) };
+}
+
+/// Starts committing the database at a path to the git repository it is in. If a commit is already running,
+/// this one is made once it's done. Does nothing if the database isn't in a git work tree, or git isn't installed.
+pub(crate) fn start_git_commit(dbpath: &str) {
+    COMMITTER.with_borrow_mut(|committer| match committer.running {
+        Some(_) => committer.queued = Some(PathBuf::from(dbpath)),
+        None => committer.running = Some(spawn_commit(PathBuf::from(dbpath))),
+    });
+}
+
+/// True while a commit is running in the background.
+pub(crate) fn git_commit_running() -> bool {
+    COMMITTER.with_borrow(|committer| committer.running.is_some())
+}
+
+/// Outcome of the commit running in the background once it's done, starting the one queued after it, if any.
+/// None while git is still running, or if nothing is being committed.
+pub(crate) fn poll_git_commit() -> Option<anyhow::Result<()>> {
+    COMMITTER.with_borrow_mut(|committer| {
+        if !committer.running.as_ref()?.is_finished() {
+            return None;
+        }
+        let result = join(committer.running.take()?);
+        committer.running = committer.queued.take().map(spawn_commit);
+        Some(result)
+    })
+}
+
+/// Waits for the commits running or queued to be done, like before exiting. Returns the first failure, if any.
+pub(crate) fn wait_for_git_commits() -> anyhow::Result<()> {
+    let mut result = Ok(());
+    while let Some(handle) = COMMITTER.with_borrow_mut(|committer| committer.running.take()) {
+        let committed = join(handle);
+        if result.is_ok() {
+            result = committed;
+        }
+        COMMITTER.with_borrow_mut(|committer| committer.running = committer.queued.take().map(spawn_commit));
+    }
+    result
+}
+
+fn spawn_commit(dbpath: PathBuf) -> JoinHandle<anyhow::Result<()>> {
+    std::thread::spawn(move || commit_db(&dbpath))
+}
+
+fn join(handle: JoinHandle<anyhow::Result<()>>) -> anyhow::Result<()> {
+    handle.join().unwrap_or_else(|_| Err(anyhow!("git commit panicked")))
+}
+
+/// Stages and commits the database alone, leaving anything else staged as it is.
+/// Saves that didn't change the database since its last commit are skipped, rather than failing to commit nothing.
+fn commit_db(dbpath: &Path) -> anyhow::Result<()> {
+    let dir = match dbpath.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = dbpath.file_name().context("Database path has no file name")?.to_string_lossy();
+    match git(dir, &["rev-parse", "--is-inside-work-tree"]) {
+        Ok(output) if output.status.success() => {}
+        _ => return Ok(()), // Not in a work tree, or no git at all.
+    }
+    run_git(dir, &["add", "--", &file_name])?;
+    if git(dir, &["diff", "--cached", "--quiet", "--", &file_name])?.status.success() {
+        return Ok(());
+    }
+    let message = format!("tdi: autosave {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+    run_git(dir, &["commit", "--quiet", "-m", &message, "--", &file_name])
+}
+
+/// Runs git in a directory, with nothing on stdin so it can't stop to ask anything.
+fn git(dir: &Path, args: &[&str]) -> std::io::Result<Output> {
+    Command::new("git").arg("-C").arg(dir).args(args).stdin(Stdio::null()).output()
+}
+
+/// Runs git in a directory, failing with what git said if it does.
+fn run_git(dir: &Path, args: &[&str]) -> anyhow::Result<()> {
+    let output = git(dir, args).context("Could not run git")?;
+    if !output.status.success() {
+        // Just the first line, to fit the bottom row, skipping git's hints on what to do
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let line = stderr.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with("hint:")).unwrap_or_default();
+        bail!("git {} failed: {line}", args[0]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir;
+
+    /// Git repository with a first commit, in a fresh directory named after the test.
+    fn git_repo(name: &str) -> PathBuf {
+        let dir = test_dir(name);
+        for args in [
+            &["init", "--quiet"][..],
+            &["config", "user.name", "tdi"],
+            &["config", "user.email", "tdi@example.com"],
+            &["config", "commit.gpgsign", "false"],
+            &["commit", "--quiet", "--allow-empty", "-m", "First"],
+        ] {
+            run_git(&dir, args).unwrap();
+        }
+        dir
+    }
+
+    /// What git prints to stdout, trimmed.
+    fn git_stdout(dir: &Path, args: &[&str]) -> String {
+        String::from_utf8(git(dir, args).unwrap().stdout).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn saves_commit_the_database_alone() {
+        let dir = git_repo("git_commit_db");
+        std::fs::write(dir.join("db.yml"), "todo_lists: []\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "staged, not committed\n").unwrap();
+        run_git(&dir, &["add", "notes.txt"]).unwrap();
+        commit_db(&dir.join("db.yml")).unwrap();
+        assert_eq!(git_stdout(&dir, &["show", "--name-only", "--format=", "HEAD"]), "db.yml");
+        assert!(git_stdout(&dir, &["log", "-1", "--format=%s"]).starts_with("tdi: autosave "));
+        assert_eq!(git_stdout(&dir, &["diff", "--cached", "--name-only"]), "notes.txt");
+    }
+
+    #[test]
+    fn unchanged_saves_are_not_committed() {
+        let dir = git_repo("git_commit_unchanged");
+        std::fs::write(dir.join("db.yml"), "todo_lists: []\n").unwrap();
+        commit_db(&dir.join("db.yml")).unwrap();
+        commit_db(&dir.join("db.yml")).unwrap();
+        assert_eq!(git_stdout(&dir, &["rev-list", "--count", "HEAD"]), "2");
+    }
+
+    #[test]
+    fn databases_outside_a_work_tree_are_left_alone() {
+        let dir = test_dir("git_commit_no_repo");
+        std::fs::write(dir.join("db.yml"), "todo_lists: []\n").unwrap();
+        commit_db(&dir.join("db.yml")).unwrap();
+        assert!(!dir.join(".git").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn git_failures_say_what_git_said_first() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = git_repo("git_commit_fails");
+        let hook = dir.join(".git/hooks/pre-commit");
+        std::fs::write(&hook, "#!/bin/sh\necho 'hint: ignore me' >&2\necho 'Database is locked' >&2\necho 'Aborting' >&2\nexit 1\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(dir.join("db.yml"), "todo_lists: []\n").unwrap();
+        let err = commit_db(&dir.join("db.yml")).unwrap_err();
+        assert_eq!(err.to_string(), "git commit failed: Database is locked");
+    }
+}
@@ -0,0 +1,183 @@
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+
+/// Undo history, as a list of snapshots plus a cursor.
+///
+/// Snapshots before the cursor are states undo returns to, most recent last.
+/// Snapshots from the cursor onwards are states redo returns to, nearest first.
+/// Undoing and redoing swap the current state with the snapshot restored, so nothing is lost either way.
+/// Recording a new snapshot discards everything from the cursor onwards, so undone changes can't be redone
+/// after a new edit.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub(crate) struct History<T> {
+    snapshots: VecDeque<T>,
+    cursor: usize,
+    #[serde(skip)]
+    max_snapshots: usize,
+}
+
+impl<T> History<T> {
+
+    pub fn new(max_snapshots: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            cursor: 0,
+            max_snapshots,
+        }
+    }
+
+    /// Records the state before a change, discarding any states that could have been redone.
+    pub fn record(&mut self, state: T) {
+        self.snapshots.truncate(self.cursor);
+        self.snapshots.push_back(state);
+        self.cursor += 1;
+        self.enforce_max_snapshots();
+    }
+
+    /// Discards the snapshot just recorded, for changes that turned out to be no-ops.
+    /// Does nothing if anything was undone since.
+    pub fn discard_last(&mut self) {
+        if self.cursor == 0 || self.cursor != self.snapshots.len() {
+            return;
+        }
+        self.snapshots.pop_back();
+        self.cursor -= 1;
+    }
+
+    /// Swaps the current state for the previous snapshot. Returns None if there is nothing to undo.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        Some(std::mem::replace(&mut self.snapshots[self.cursor], current))
+    }
+
+    /// Swaps the current state for the next snapshot. Returns None if there is nothing to redo.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        if self.cursor == self.snapshots.len() {
+            return None;
+        }
+        let snapshot = std::mem::replace(&mut self.snapshots[self.cursor], current);
+        self.cursor += 1;
+        Some(snapshot)
+    }
+
+    /// Changes the number of snapshots kept, dropping the oldest if there are now too many.
+    pub fn set_max_snapshots(&mut self, max_snapshots: usize) {
+        self.max_snapshots = max_snapshots;
+        self.cursor = self.cursor.min(self.snapshots.len());
+        self.enforce_max_snapshots();
+    }
+
+    /// Drops the oldest undo snapshots while there are too many, then the furthest redo snapshots.
+    fn enforce_max_snapshots(&mut self) {
+        while self.snapshots.len() > self.max_snapshots {
+            if self.cursor > 0 {
+                self.snapshots.pop_front();
+                self.cursor -= 1;
+            }
+            else {
+                self.snapshots.pop_back();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// History with states recorded in order, as if each was changed into the next.
+    fn history_of(states: &[u32], max_snapshots: usize) -> History<u32> {
+        let mut history = History::new(max_snapshots);
+        for &state in states {
+            history.record(state);
+        }
+        history
+    }
+
+    #[test]
+    fn undo_and_redo_swap_states() {
+        let mut history = history_of(&[0, 1], 10);
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+        assert_eq!(history.undo(0), None);
+        assert_eq!(history.redo(0), Some(1));
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), None);
+    }
+
+    #[test]
+    fn editing_after_undo_drops_the_redo_branch() {
+        let mut history = history_of(&[0, 1, 2], 10);
+        assert_eq!(history.undo(3), Some(2));
+        assert_eq!(history.undo(2), Some(1));
+        history.record(1); // Edited 1 into 10, instead of into 2.
+        assert_eq!(history.redo(10), None);
+        assert_eq!(history.undo(10), Some(1));
+        assert_eq!(history.redo(1), Some(10));
+        assert_eq!(history.redo(10), None);
+        assert_eq!(history.undo(10), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+        assert_eq!(history.undo(0), None);
+    }
+
+    #[test]
+    fn discard_last_forgets_the_snapshot_just_recorded() {
+        let mut history = history_of(&[0, 1], 10);
+        history.discard_last();
+        assert_eq!(history, history_of(&[0], 10));
+        assert_eq!(history.undo(1), Some(0));
+        assert_eq!(history.undo(0), None);
+
+        // Snapshots that could be redone are kept, since nothing was recorded since the undo.
+        let mut history = history_of(&[0, 1], 10);
+        history.undo(2);
+        history.discard_last();
+        assert_eq!(history.redo(1), Some(2));
+
+        let mut history = History::<u32>::new(10);
+        history.discard_last();
+        assert_eq!(history, History::new(10));
+    }
+
+    #[test]
+    fn recording_past_the_limit_drops_the_oldest() {
+        let mut history = history_of(&[0, 1, 2, 3], 2);
+        assert_eq!(history.undo(4), Some(3));
+        assert_eq!(history.undo(3), Some(2));
+        assert_eq!(history.undo(2), None);
+    }
+
+    #[test]
+    fn shrinking_mid_history_drops_the_oldest_undo_then_the_furthest_redo() {
+        let mut history = history_of(&[0, 1, 2, 3, 4], 10);
+        assert_eq!(history.undo(5), Some(4));
+        assert_eq!(history.undo(4), Some(3));
+
+        // Three undo steps and two redo steps left, so the oldest undo steps go first.
+        history.set_max_snapshots(3);
+        let mut shrunk = history.clone();
+        assert_eq!(shrunk.undo(3), Some(2));
+        assert_eq!(shrunk.undo(2), None);
+        assert_eq!(shrunk.redo(2), Some(3));
+        assert_eq!(shrunk.redo(3), Some(4));
+        assert_eq!(shrunk.redo(4), Some(5));
+        assert_eq!(shrunk.redo(5), None);
+
+        // Then redo steps from the furthest, once there are no undo steps left.
+        history.set_max_snapshots(1);
+        assert_eq!(history.undo(3), None);
+        assert_eq!(history.redo(3), Some(4));
+        assert_eq!(history.redo(4), None);
+    }
+
+    #[test]
+    fn cursor_stays_in_bounds_when_loaded_past_the_end() {
+        let mut history: History<u32> = serde_yaml::from_str("snapshots: [0, 1]\ncursor: 5\n").unwrap();
+        history.set_max_snapshots(10);
+        assert_eq!(history.redo(2), None);
+        assert_eq!(history.undo(2), Some(1));
+    }
+}
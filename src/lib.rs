@@ -1,6 +1,36 @@
 mod app;
+mod clipboard;
 pub mod color;
+mod export;
+mod finder;
+mod git;
+mod history;
+mod migrate;
+mod paths;
+mod picker;
+mod popup;
+mod sqlite;
+mod storage;
+#[cfg(test)]
+mod testing;
+mod text;
+mod theme;
 mod todo;
 
 pub use app::*;
+use clipboard::*;
+use export::*;
+use finder::*;
+use git::*;
+use history::*;
+use migrate::*;
+use paths::*;
+use picker::*;
+use popup::*;
+use sqlite::*;
+use storage::*;
+#[cfg(test)]
+use testing::*;
+use text::*;
+use theme::*;
 use todo::*;
@@ -1,18 +1,122 @@
-use tdi::App;
+use tdi::{App, Options, APP_VERSION};
+
+const USAGE: &str = "\
+Usage: tdi [options]
+
+Options:
+  --db <path>       Edit the todo database at <path> instead of the config's
+  --config <path>   Read the config from <path> instead of the config directory
+  --profile <name>  Use the config and database of profile <name>, kept apart from the usual ones
+  --list-profiles   Print the names of the profiles, then exit
+  --readonly        View the board without ever changing the database
+  --import <path>   Copy the todos of the database at <path> into a new database, then exit.
+                    For a path ending in .md, add its task items to the database instead
+  --export <path>   Write the board to <path> as Markdown or CSV, for a path ending in .md or .csv, then exit
+  -h, --help        Print this help
+  -V, --version     Print the version
+
+Environment:
+  TDI_DB            Like --db, which takes precedence
+  TDI_CONFIG        Like --config, which takes precedence
+  TDI_PROFILE       Like --profile, which takes precedence
+
+--db and --config, and their variables, take precedence over the profile's files.";
 
 fn main() {
     if let Err(err) = run() {
-        eprintln!("{err}");
+        eprintln!("{err:#}");
+        std::process::exit(1);
     }
 }
 
 fn run() -> anyhow::Result<()> {
-    let app = App::init()?;
+    let Some(options) = parse_args(std::env::args().skip(1))? else {
+        return Ok(());
+    };
+    if let Some(import_path) = &options.import_path {
+        println!("{}", App::import(&options, import_path)?);
+        return Ok(());
+    }
+    if let Some(export_path) = &options.export_path {
+        println!("{}", App::export(&options, export_path, confirm_overwrite)?);
+        return Ok(());
+    }
+    let app = App::init(options)?;
     let terminal = ratatui::init();
-    if let Err(err) = app.run(terminal) {
-        eprintln!("{err}");
+    let result = app.run(terminal);
+    ratatui::restore(); // Before reporting errors, so they are printed to the normal screen.
+    result
+}
+
+/// Options from command line arguments, or None if tdi shouldn't start, like after printing help.
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Option<Options>> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg, None),
+        };
+        let mut value = |what: &str| {
+            inline_value.clone().or_else(|| args.next()).ok_or_else(|| anyhow::anyhow!("{flag} needs {what}\n\n{USAGE}"))
+        };
+        match flag.as_str() {
+            "--db" => options.dbpath = Some(value("a path")?),
+            "--config" => options.config_path = Some(value("a path")?),
+            "--import" => options.import_path = Some(value("a path")?),
+            "--export" => options.export_path = Some(value("a path")?),
+            "--readonly" => options.read_only = true,
+            "--profile" => options.profile = Some(value("a profile name")?),
+            "--list-profiles" => {
+                for profile in App::list_profiles()? {
+                    println!("{profile}");
+                }
+                return Ok(None);
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(None);
+            }
+            "-V" | "--version" => {
+                println!("tdi {APP_VERSION}");
+                return Ok(None);
+            }
+            _ => anyhow::bail!("Unknown argument {flag}\n\n{USAGE}"),
+        }
+    }
+    Ok(Some(options))
+}
+
+/// Asks on the terminal whether to overwrite an existing file. Anything but y, including no answer at all
+/// when not run interactively, leaves it alone.
+fn confirm_overwrite(path: &str) -> bool {
+    eprint!("{path} already exists. Overwrite it? (y/n) ");
+    let mut answer = String::new();
+    match std::io::stdin().read_line(&mut answer) {
+        Ok(_) => matches!(answer.trim(), "y" | "Y" | "yes"),
+        Err(_) => false,
     }
-    ratatui::restore();
-    Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Option<Options>> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn flags_without_a_value_say_what_they_need() {
+        for (flag, needed) in [("--db", "a path"), ("--export", "a path"), ("--profile", "a profile name")] {
+            let err = parse(&[flag]).unwrap_err().to_string();
+            assert!(err.starts_with(&format!("{flag} needs {needed}\n")), "{err}");
+        }
+    }
+
+    #[test]
+    fn values_follow_flags_or_an_equals_sign() {
+        let options = parse(&["--profile", "work", "--db=~/db.yml"]).unwrap().unwrap();
+        assert_eq!(options.profile.as_deref(), Some("work"));
+        assert_eq!(options.dbpath.as_deref(), Some("~/db.yml"));
+    }
+}
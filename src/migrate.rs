@@ -0,0 +1,151 @@
+use crate::{APP_VERSION, parse_version};
+use anyhow::bail;
+use serde_yaml::{Mapping, Value};
+
+/// Version assumed for databases without one, the first to be released.
+const FIRST_VERSION: &str = "0.1";
+
+/// Function changing a database in place, from the format before a version to that version's.
+type Migration = fn(&mut Mapping);
+
+/// Changes to the database format, each bringing a database saved by an earlier version up to the one
+/// it's listed with. Oldest first. A migration is needed whenever a field is added, renamed, or changes meaning.
+const MIGRATIONS: &[(&str, Migration)] = &[
+    ("0.2", add_marks),
+];
+
+/// Version of tdi a database was last saved by.
+/// Versions written as numbers, like `version: 0.2` typed by hand, are read as the same text.
+fn db_version(db: &Value) -> String {
+    match db.get("version") {
+        Some(Value::String(version)) => version.clone(),
+        Some(Value::Number(version)) => version.to_string(),
+        _ => FIRST_VERSION.to_string(),
+    }
+}
+
+/// Brings a parsed database up to the format of this version of tdi, by running every migration
+/// newer than the version it was saved by, in order.
+/// Databases saved by a newer version are refused rather than read, since fields this version doesn't know of
+/// would be dropped on the next save. The version itself is kept, written out as text, so it still says which
+/// version was last seen.
+pub(crate) fn migrate_db(db: &mut Value) -> anyhow::Result<()> {
+    let version = db_version(db);
+    check_db_version(&version)?;
+    let Value::Mapping(mapping) = db else { return Ok(()) }; // Left for parsing to report.
+    mapping.insert("version".into(), Value::String(version.clone()));
+    for (migration_version, migration) in MIGRATIONS {
+        if parse_version(migration_version) > parse_version(&version) {
+            migration(mapping);
+        }
+    }
+    Ok(())
+}
+
+/// Refuses databases saved by a newer version of tdi than this one.
+pub(crate) fn check_db_version(version: &str) -> anyhow::Result<()> {
+    if parse_version(version) > parse_version(APP_VERSION) {
+        bail!("Database was saved by tdi {version}, which is newer than this tdi {APP_VERSION}. Update tdi to open it");
+    }
+    Ok(())
+}
+
+/// 0.2 added marks, jumped to with '. Todos got ids to mark them by, assigned when loaded.
+fn add_marks(db: &mut Mapping) {
+    if !db.contains_key("marks") {
+        db.insert("marks".into(), Value::Mapping(Mapping::new()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{State, Todo, TodoList};
+
+    /// Database saved by every version of tdi there has been, oldest first, with the version that saved it.
+    /// Each holds the same board, as far as that version could tell.
+    const FIXTURES: &[(&str, &str)] = &[
+        (FIRST_VERSION, include_str!("../tests/fixtures/db-unversioned.yml")),
+        ("0.1", include_str!("../tests/fixtures/db-0.1.yml")),
+        ("0.2", include_str!("../tests/fixtures/db-0.2.yml")),
+    ];
+
+    fn fixture(version: &str) -> Mapping {
+        let (_, contents) = FIXTURES.iter().rev().find(|(fixture_version, _)| *fixture_version == version).unwrap();
+        serde_yaml::from_str(contents).unwrap()
+    }
+
+    fn migrated(contents: &str) -> State {
+        let mut db: Value = serde_yaml::from_str(contents).unwrap();
+        migrate_db(&mut db).unwrap();
+        serde_yaml::from_value(db).unwrap()
+    }
+
+    #[test]
+    fn add_marks_adds_no_marks() {
+        let mut db = fixture("0.1");
+        add_marks(&mut db);
+        assert_eq!(db.get("marks"), Some(&Value::Mapping(Mapping::new())));
+        let mut without_marks = db.clone();
+        without_marks.remove("marks");
+        assert_eq!(without_marks, fixture("0.1"));
+    }
+
+    #[test]
+    fn add_marks_keeps_marks_there_are() {
+        let mut db = fixture("0.2");
+        add_marks(&mut db);
+        assert_eq!(db, fixture("0.2"));
+    }
+
+    #[test]
+    fn every_version_loads_as_the_same_board() {
+        let todos = |ids: [u64; 2]| vec![
+            Todo { id: ids[0], name: "Buy milk".to_string(), marked: false },
+            Todo { id: ids[1], name: "Call Ana: about the trip".to_string(), marked: true },
+        ];
+        for (version, contents) in FIXTURES {
+            let state = migrated(contents);
+            let (ids, marks) = match state.todo_lists[0].todos[0].id {
+                0 => ([0, 0], [].into()), // Assigned when loaded by the app, before 0.2.
+                _ => ([1, 2], [('a', 2)].into()),
+            };
+            assert_eq!(state.version, *version);
+            assert_eq!(state.todo_lists, [
+                TodoList { name: "Todo".to_string(), todos: todos(ids) },
+                TodoList { name: "Backlog".to_string(), todos: vec![] },
+            ], "{version}");
+            assert_eq!(state.marks, marks, "{version}");
+        }
+    }
+
+    #[test]
+    fn there_is_a_fixture_for_every_migration_and_this_version() {
+        let fixture_versions: Vec<&str> = FIXTURES.iter().map(|(version, _)| *version).collect();
+        for (version, _) in MIGRATIONS {
+            assert!(fixture_versions.contains(version), "no fixture for {version}");
+        }
+        assert_eq!(fixture_versions.last(), Some(&APP_VERSION));
+    }
+
+    #[test]
+    fn migrations_are_in_order() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(parse_version(pair[0].0) < parse_version(pair[1].0), "{} before {}", pair[0].0, pair[1].0);
+        }
+        assert!(MIGRATIONS.iter().all(|(version, _)| parse_version(version) <= parse_version(APP_VERSION)));
+    }
+
+    #[test]
+    fn versions_written_as_numbers_are_read_as_text() {
+        let state = migrated("version: 0.1\ntodo_lists: []\n");
+        assert_eq!((state.version.as_str(), state.marks.len()), ("0.1", 0));
+    }
+
+    #[test]
+    fn newer_databases_are_refused() {
+        let mut db: Value = serde_yaml::from_str("version: '99.0'\ntodo_lists: []\n").unwrap();
+        let err = migrate_db(&mut db).unwrap_err();
+        assert!(err.to_string().contains("saved by tdi 99.0, which is newer than this tdi"), "{err}");
+    }
+}
@@ -0,0 +1,243 @@
+use crate::Options;
+use anyhow::{Context, bail};
+use std::path::{Path, PathBuf};
+
+/// Files named explicitly, rather than found in the usual directories, and the profile to find them in otherwise.
+/// Command line options take precedence over the TDI_CONFIG, TDI_DB and TDI_PROFILE environment variables.
+#[derive(Clone, Eq, PartialEq, Default, Debug)]
+pub(crate) struct Paths {
+    pub config: Option<String>,     // Config file to use instead of the config directory's.
+    pub db: Option<String>,         // Database to edit instead of the config's.
+    pub profile: Option<String>,    // Profile whose directories to use instead of the usual ones.
+}
+
+impl Paths {
+
+    /// Paths from command line options, falling back to environment variables looked up with `var`.
+    /// Empty variables count as unset.
+    pub fn resolve(options: &Options, var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name| var(name).filter(|value| !value.is_empty());
+        Self {
+            config: options.config_path.clone().or_else(|| var("TDI_CONFIG")),
+            db: options.dbpath.clone().or_else(|| var("TDI_DB")),
+            profile: options.profile.clone().or_else(|| var("TDI_PROFILE")),
+        }
+    }
+}
+
+/// Directory of the config file: %APPDATA%\tdi on Windows, ~/Library/Application Support/tdi on macOS,
+/// and ~/.config/tdi elsewhere. On macOS, ~/.config/tdi is used instead if it already exists.
+pub(crate) fn config_dir() -> anyhow::Result<PathBuf> {
+    platform_dir(".config")
+}
+
+/// Directory of the database: %APPDATA%\tdi on Windows, ~/Library/Application Support/tdi on macOS,
+/// and ~/.local/share/tdi elsewhere. On macOS, ~/.local/share/tdi is used instead if it already exists.
+pub(crate) fn data_dir() -> anyhow::Result<PathBuf> {
+    platform_dir(".local/share")
+}
+
+/// Directory of a profile's config and database, named after it inside `dir`, the directory used without a profile.
+/// Like ~/.config/tdi/work for the config of a profile named work.
+pub(crate) fn profile_dir(dir: PathBuf, profile: Option<&str>) -> anyhow::Result<PathBuf> {
+    let Some(profile) = profile else { return Ok(dir) };
+    if profile.is_empty() || profile.starts_with('.') || profile.contains(['/', '\\']) {
+        bail!("Invalid profile name '{profile}'. Use a name without slashes that doesn't start with a dot");
+    }
+    Ok(dir.join(profile))
+}
+
+/// Names of the profiles there are, which are the directories in the config directory, sorted.
+pub(crate) fn list_profiles() -> anyhow::Result<Vec<String>> {
+    let config_dir = config_dir()?;
+    let entries = match std::fs::read_dir(&config_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err).with_context(|| format!("Could not list {}", config_dir.display())),
+    };
+    let mut profiles = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_dir() && !name.starts_with('.') {
+            profiles.push(name);
+        }
+    }
+    profiles.sort();
+    Ok(profiles)
+}
+
+/// Directories tdi's own are found under, as the `dirs` crate finds them on the platform running.
+/// Kept apart so other platforms and home directories can be tried out.
+struct BaseDirs {
+    os: &'static str,               // Like "linux", "macos" or "windows".
+    home: Option<PathBuf>,          // Home directory of the user.
+    config: Option<PathBuf>,        // %APPDATA% on Windows, ~/Library/Application Support on macOS.
+}
+
+impl BaseDirs {
+    fn current() -> Self {
+        Self {
+            os: std::env::consts::OS,
+            home: dirs::home_dir(),
+            config: dirs::config_dir(),
+        }
+    }
+}
+
+/// The platform's directory for tdi's files, or the XDG-style one under the home directory,
+/// like ".config", on platforms without a convention of their own.
+fn platform_dir(xdg_dir: &str) -> anyhow::Result<PathBuf> {
+    platform_dir_in(&BaseDirs::current(), xdg_dir)
+}
+
+fn platform_dir_in(base_dirs: &BaseDirs, xdg_dir: &str) -> anyhow::Result<PathBuf> {
+    if base_dirs.os == "windows" {
+        let app_data = base_dirs.config.as_ref().context("Could not find the AppData directory")?;
+        return Ok(app_data.join("tdi"));
+    }
+    let home_dir = base_dirs.home.as_ref().context("Could not find the home directory")?;
+    let xdg_style = home_dir.join(xdg_dir).join("tdi");
+    if base_dirs.os == "macos" && !xdg_style.exists() {
+        let app_support = base_dirs.config.as_ref().context("Could not find the Application Support directory")?;
+        return Ok(app_support.join("tdi"));
+    }
+    Ok(xdg_style)
+}
+
+/// Path with a leading ~ replaced by the home directory, like the shell does.
+pub(crate) fn expand_tilde(path: &str) -> String {
+    expand_tilde_in(path, dirs::home_dir().as_deref())
+}
+
+fn expand_tilde_in(path: &str, home_dir: Option<&Path>) -> String {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return path.to_string(),
+    };
+    match home_dir {
+        Some(home_dir) => format!("{}{rest}", home_dir.display()),
+        None => path.to_string(),
+    }
+}
+
+/// Path with the home directory replaced by a leading ~, for showing it shorter.
+pub(crate) fn contract_tilde(path: &str) -> String {
+    contract_tilde_in(path, dirs::home_dir().as_deref())
+}
+
+fn contract_tilde_in(path: &str, home_dir: Option<&Path>) -> String {
+    let Some(home_dir) = home_dir else { return path.to_string() };
+    match Path::new(path).strip_prefix(home_dir) {
+        Ok(rest) if !home_dir.as_os_str().is_empty() => format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display()),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir;
+
+    #[test]
+    fn options_take_precedence_over_the_environment() {
+        type Case<'a> = (Option<&'a str>, Option<&'a str>, Option<&'a str>);
+        // The option, the environment variable, and which should be used.
+        let cases: &[Case] = &[
+            (None, None, None),
+            (None, Some(""), None),
+            (None, Some("env"), Some("env")),
+            (Some("option"), None, Some("option")),
+            (Some("option"), Some(""), Some("option")),
+            (Some("option"), Some("env"), Some("option")),
+        ];
+        for (name, set) in [("TDI_CONFIG", 0), ("TDI_DB", 1), ("TDI_PROFILE", 2)] {
+            for &(option, env, expected) in cases {
+                let mut options = Options::default();
+                let field = match set {
+                    0 => &mut options.config_path,
+                    1 => &mut options.dbpath,
+                    _ => &mut options.profile,
+                };
+                *field = option.map(str::to_string);
+                let paths = Paths::resolve(&options, |var| (var == name).then(|| env.map(str::to_string)).flatten());
+                let resolved = [&paths.config, &paths.db, &paths.profile];
+                for (i, value) in resolved.into_iter().enumerate() {
+                    let expected = if i == set { expected } else { None };
+                    assert_eq!(value.as_deref(), expected, "{name} with option {option:?} and variable {env:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn each_variable_sets_its_own_path() {
+        let vars = |name: &str| Some(format!("{name} value"));
+        let paths = Paths::resolve(&Options::default(), vars);
+        assert_eq!(paths, Paths {
+            config: Some("TDI_CONFIG value".to_string()),
+            db: Some("TDI_DB value".to_string()),
+            profile: Some("TDI_PROFILE value".to_string()),
+        });
+    }
+
+    fn base_dirs(os: &'static str, home: &Path) -> BaseDirs {
+        BaseDirs {
+            os,
+            home: Some(home.to_path_buf()),
+            config: Some(home.join("AppConfig")),
+        }
+    }
+
+    #[test]
+    fn platform_dirs_follow_each_platforms_convention() {
+        let home = test_dir("platform_dirs");
+        let linux = base_dirs("linux", &home);
+        assert_eq!(platform_dir_in(&linux, ".config").unwrap(), home.join(".config/tdi"));
+        assert_eq!(platform_dir_in(&linux, ".local/share").unwrap(), home.join(".local/share/tdi"));
+        let windows = base_dirs("windows", &home);
+        assert_eq!(platform_dir_in(&windows, ".config").unwrap(), home.join("AppConfig/tdi"));
+        assert_eq!(platform_dir_in(&windows, ".local/share").unwrap(), home.join("AppConfig/tdi"));
+        let macos = base_dirs("macos", &home);
+        assert_eq!(platform_dir_in(&macos, ".config").unwrap(), home.join("AppConfig/tdi"));
+    }
+
+    #[test]
+    fn macos_keeps_using_xdg_style_dirs_that_exist() {
+        let home = test_dir("macos_xdg_dirs");
+        std::fs::create_dir_all(home.join(".config/tdi")).unwrap();
+        let macos = base_dirs("macos", &home);
+        assert_eq!(platform_dir_in(&macos, ".config").unwrap(), home.join(".config/tdi"));
+        assert_eq!(platform_dir_in(&macos, ".local/share").unwrap(), home.join("AppConfig/tdi"));
+    }
+
+    #[test]
+    fn platform_dirs_fail_without_a_home_or_config_dir() {
+        let nowhere = BaseDirs { os: "linux", home: None, config: None };
+        assert!(platform_dir_in(&nowhere, ".config").unwrap_err().to_string().contains("home directory"));
+        let nowhere = BaseDirs { os: "windows", ..nowhere };
+        assert!(platform_dir_in(&nowhere, ".config").unwrap_err().to_string().contains("AppData"));
+    }
+
+    #[test]
+    fn tilde_expands_to_the_home_dir() {
+        let home = Some(Path::new("/home/ana"));
+        assert_eq!(expand_tilde_in("~", home), "/home/ana");
+        assert_eq!(expand_tilde_in("~/todo/db.yml", home), "/home/ana/todo/db.yml");
+        assert_eq!(expand_tilde_in("~\\db.yml", home), "/home/ana\\db.yml");
+        assert_eq!(expand_tilde_in("~bob/db.yml", home), "~bob/db.yml");
+        assert_eq!(expand_tilde_in("/tmp/~/db.yml", home), "/tmp/~/db.yml");
+        assert_eq!(expand_tilde_in("~/db.yml", None), "~/db.yml");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn home_dir_contracts_to_tilde() {
+        let home = Some(Path::new("/home/ana"));
+        assert_eq!(contract_tilde_in("/home/ana/todo/db.yml", home), "~/todo/db.yml");
+        assert_eq!(contract_tilde_in("/home/anabel/db.yml", home), "/home/anabel/db.yml");
+        assert_eq!(contract_tilde_in("/tmp/db.yml", home), "/tmp/db.yml");
+        assert_eq!(contract_tilde_in("/home/ana/db.yml", None), "/home/ana/db.yml");
+        assert_eq!(contract_tilde_in("/db.yml", Some(Path::new(""))), "/db.yml");
+    }
+}
@@ -0,0 +1,76 @@
+use crate::Theme;
+use ratatui::Frame;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear};
+use unicode_width::UnicodeWidthStr;
+
+/// Small popup for choosing one item from a list, like the todo list to send a todo to.
+#[derive(Clone, Eq, PartialEq, Default, Debug)]
+pub(crate) struct Picker {
+    pub title: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+}
+
+impl Picker {
+
+    pub fn new(title: impl Into<String>, items: Vec<String>, selected: usize) -> Self {
+        let selected = selected.min(items.len().saturating_sub(1));
+        Self {
+            title: title.into(),
+            items,
+            selected,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.items.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn render(&self, theme: &Theme, area: Rect, frame: &mut Frame) {
+        let longest = self.items.iter().map(|item| item.width()).max().unwrap_or(0);
+        let width = (longest as u16 + 8).max(self.title.width() as u16 + 4).min(area.width);
+        let height = (self.items.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let block = Block::default()
+            .title(self.title.as_str())
+            .borders(Borders::all())
+            .title_alignment(Alignment::Center)
+            .fg(theme.border_selected);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(block, popup_area);
+        if popup_area.width < 4 || popup_area.height < 3 {
+            return;
+        }
+
+        // Items, numbered for quick selection
+        let mut line_area = Rect {
+            x: popup_area.x + 2,
+            y: popup_area.y,
+            width: popup_area.width - 4,
+            height: 1,
+        };
+        for (i, item) in self.items.iter().enumerate().take(popup_area.height as usize - 2) {
+            let (bg_color, fg_color) = match i == self.selected {
+                false => (theme.bg_unselected, theme.fg_unselected),
+                true => (theme.bg_selected, theme.fg_selected),
+            };
+            line_area.y += 1;
+            let item_line = Line::from(format!("{} {item}", i + 1)).bg(bg_color).fg(fg_color);
+            frame.render_widget(item_line, line_area);
+        }
+    }
+}
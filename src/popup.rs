@@ -0,0 +1,78 @@
+use crate::Theme;
+use ratatui::Frame;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+use unicode_width::UnicodeWidthStr;
+
+/// Read-only popup displaying lines of text, like the "what's new" screen.
+/// Text too long to fit can be scrolled.
+#[derive(Clone, Eq, PartialEq, Default, Debug)]
+pub(crate) struct Popup {
+    pub title: String,
+    pub lines: Vec<String>,
+    scroll: u16,        // Number of lines scrolled past.
+}
+
+impl Popup {
+
+    pub fn new(title: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            scroll: 0,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if (self.scroll as usize) + 1 < self.lines.len() {
+            self.scroll += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    pub fn render(&self, theme: &Theme, area: Rect, frame: &mut Frame) {
+        let width = (area.width * 3 / 5).max(30).min(area.width);
+        let height = (self.lines.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        self.render_in(theme, popup_area, frame);
+    }
+
+    /// Renders the popup in the bottom right corner, sized to its text, like hints that shouldn't hide much.
+    pub fn render_corner(&self, theme: &Theme, area: Rect, frame: &mut Frame) {
+        let longest = self.lines.iter().chain([&self.title]).map(|line| line.width()).max().unwrap_or(0);
+        let width = (longest as u16 + 4).min(area.width);
+        let height = (self.lines.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.right() - width,
+            y: area.bottom() - height,
+            width,
+            height,
+        };
+        self.render_in(theme, popup_area, frame);
+    }
+
+    fn render_in(&self, theme: &Theme, popup_area: Rect, frame: &mut Frame) {
+        let block = Block::default()
+            .title(self.title.as_str())
+            .borders(Borders::all())
+            .title_alignment(Alignment::Center)
+            .fg(theme.border_selected);
+        let text = self.lines.join("\n");
+        let paragraph = Paragraph::new(text)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .fg(theme.fg_unselected);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(paragraph, popup_area);
+    }
+}
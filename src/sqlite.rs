@@ -0,0 +1,289 @@
+/// Database kept in SQLite, one row per todo list, todo and mark, so the board can be queried and edited with
+/// other tools. Each save rewrites the rows inside a single transaction, so readers never see half of one.
+pub(crate) struct SqliteFile {
+    pub path: String,
+}
+
+#[cfg(feature = "sqlite")]
+mod backend {
+    use super::SqliteFile;
+    use crate::{APP_VERSION, DbFormat, LoadError, State, Storage, Todo, TodoList, check_db_version, replace_atomic};
+    use anyhow::anyhow;
+    use rusqlite::{Connection, ErrorCode, OpenFlags, OptionalExtension, TransactionBehavior};
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// How long to wait for another process writing the database to finish.
+    const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS lists (
+            position INTEGER PRIMARY KEY,
+            name TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS todos (
+            list INTEGER NOT NULL REFERENCES lists (position),
+            position INTEGER NOT NULL,
+            id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            marked INTEGER NOT NULL,
+            PRIMARY KEY (list, position)
+        );
+        CREATE TABLE IF NOT EXISTS marks (
+            register TEXT PRIMARY KEY,
+            todo INTEGER NOT NULL
+        );
+    ";
+
+    impl Storage for SqliteFile {
+        fn load(&self) -> Result<State, LoadError> {
+            let connection = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(load_error)?;
+            connection.busy_timeout(BUSY_TIMEOUT).map_err(load_error)?;
+            let version: Option<String> = connection
+                .query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| row.get(0))
+                .optional()
+                .map_err(load_error)?;
+            let version = version.ok_or_else(|| LoadError::Corrupt(anyhow!("Database has no version")))?;
+            check_db_version(&version).map_err(LoadError::Unreadable)?;
+            let mut state = State::default();
+            state.version = version;
+            state.todo_lists.clear();
+
+            // Lists, then their todos, by position
+            let mut positions = vec![];
+            let mut statement = connection.prepare("SELECT position, name FROM lists ORDER BY position").map_err(load_error)?;
+            let mut rows = statement.query([]).map_err(load_error)?;
+            while let Some(row) = rows.next().map_err(load_error)? {
+                positions.push(row.get::<_, i64>(0).map_err(load_error)?);
+                state.todo_lists.push(TodoList {
+                    name: row.get(1).map_err(load_error)?,
+                    todos: vec![],
+                });
+            }
+            let mut statement = connection
+                .prepare("SELECT list, id, name, marked FROM todos ORDER BY list, position")
+                .map_err(load_error)?;
+            let mut rows = statement.query([]).map_err(load_error)?;
+            while let Some(row) = rows.next().map_err(load_error)? {
+                let list: i64 = row.get(0).map_err(load_error)?;
+                let Some(todo_list_idx) = positions.iter().position(|&position| position == list) else {
+                    return Err(LoadError::Corrupt(anyhow!("Todo in list {list}, which doesn't exist")));
+                };
+                state.todo_lists[todo_list_idx].todos.push(Todo {
+                    id: row.get(1).map_err(load_error)?,
+                    name: row.get(2).map_err(load_error)?,
+                    marked: row.get(3).map_err(load_error)?,
+                });
+            }
+
+            let mut statement = connection.prepare("SELECT register, todo FROM marks").map_err(load_error)?;
+            let mut rows = statement.query([]).map_err(load_error)?;
+            while let Some(row) = rows.next().map_err(load_error)? {
+                let register: String = row.get(0).map_err(load_error)?;
+                let mut chars = register.chars();
+                let (Some(register), None) = (chars.next(), chars.next()) else {
+                    return Err(LoadError::Corrupt(anyhow!("Mark '{register}' isn't a single character")));
+                };
+                state.marks.insert(register, row.get(1).map_err(load_error)?);
+            }
+            Ok(state)
+        }
+
+        /// Rows are written in place, taking SQLite's write lock for the whole save. A database of another format
+        /// at the path, being converted, is replaced as a whole instead, once the new one is written.
+        fn save(&self, state: &State) -> anyhow::Result<()> {
+            let path = Path::new(&self.path);
+            match DbFormat::detect(&self.path) {
+                Ok(DbFormat::Sqlite) => write_rows(path, state),
+                Ok(_) => replace_atomic(path, |tmp_path| write_rows(tmp_path, state)),
+                Err(_) => write_rows(path, state),
+            }
+        }
+    }
+
+    /// Replaces the rows of the database at a path with the state's, creating the database if needed.
+    fn write_rows(path: &Path, state: &State) -> anyhow::Result<()> {
+        let mut connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        transaction.execute_batch(SCHEMA)?;
+        transaction.execute_batch("DELETE FROM todos; DELETE FROM lists; DELETE FROM marks;")?;
+        transaction.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('version', ?1)", [APP_VERSION])?;
+        {
+            let mut insert_list = transaction.prepare("INSERT INTO lists (position, name) VALUES (?1, ?2)")?;
+            let mut insert_todo = transaction
+                .prepare("INSERT INTO todos (list, position, id, name, marked) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+            for (todo_list_idx, todo_list) in state.todo_lists.iter().enumerate() {
+                insert_list.execute((todo_list_idx, &todo_list.name))?;
+                for (todo_idx, todo) in todo_list.todos.iter().enumerate() {
+                    insert_todo.execute((todo_list_idx, todo_idx, todo.id, &todo.name, todo.marked))?;
+                }
+            }
+            let mut insert_mark = transaction.prepare("INSERT INTO marks (register, todo) VALUES (?1, ?2)")?;
+            for (register, todo_id) in &state.marks {
+                insert_mark.execute((register.to_string(), todo_id))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Tells apart databases that aren't usable, which are corrupt, from ones that can't be read right now,
+    /// like while another process holds a lock on them.
+    fn load_error(err: rusqlite::Error) -> LoadError {
+        let corrupt = match &err {
+            rusqlite::Error::SqliteFailure(failure, _) => matches!(
+                failure.code,
+                ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt | ErrorCode::Unknown, // Unknown is a missing table or column.
+            ),
+            rusqlite::Error::InvalidColumnType(..) |
+            rusqlite::Error::FromSqlConversionFailure(..) |
+            rusqlite::Error::IntegralValueOutOfRange(..) => true,
+            _ => false,
+        };
+        match corrupt {
+            true => LoadError::Corrupt(err.into()),
+            false => LoadError::Unreadable(err.into()),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test_dir;
+
+        fn state() -> State {
+            let mut state = State::default();
+            state.todo_lists[0].todos = vec![
+                Todo { id: 1, ..Todo::new("Buy milk") },
+                Todo { id: 2, marked: true, ..Todo::new("Call Ana, 'about' the trip; DROP TABLE todos") },
+            ];
+            state.todo_lists[1].todos = vec![Todo { id: 3, ..Todo::new("日本語 👩‍💻\nsecond line") }];
+            state.todo_lists.push(TodoList { name: "Empty".to_string(), todos: vec![] });
+            state.marks.insert('a', 2);
+            state.marks.insert('Z', 3);
+            state
+        }
+
+        fn load(path: &Path) -> Result<State, LoadError> {
+            SqliteFile { path: path.to_string_lossy().into_owned() }.load()
+        }
+
+        fn save(path: &Path, state: &State) {
+            SqliteFile { path: path.to_string_lossy().into_owned() }.save(state).unwrap();
+        }
+
+        /// Database made by running some SQL, for databases tdi wouldn't write itself.
+        fn sqlite_db(dir: &Path, sql: &str) -> std::path::PathBuf {
+            let path = dir.join("db.sqlite");
+            Connection::open(&path).unwrap().execute_batch(&format!("{SCHEMA}{sql}")).unwrap();
+            path
+        }
+
+        fn assert_corrupt(result: Result<State, LoadError>, message: &str) {
+            match result {
+                Err(LoadError::Corrupt(err)) => assert!(format!("{err:#}").contains(message), "{err:#}"),
+                other => panic!("expected a corrupt database, got {other:?}"),
+            }
+        }
+
+        #[test]
+        fn states_round_trip_through_sqlite() {
+            let dir = test_dir("sqlite_round_trip");
+            let path = dir.join("db.sqlite");
+            let mut state = state();
+            save(&path, &state);
+            assert_eq!(load(&path).unwrap(), state);
+            assert_eq!(DbFormat::detect(&path.to_string_lossy()).unwrap(), DbFormat::Sqlite);
+
+            // Saving again replaces every row, leaving none behind.
+            state.todo_lists.truncate(1);
+            state.todo_lists[0].todos.remove(0);
+            state.marks.remove(&'Z');
+            save(&path, &state);
+            assert_eq!(load(&path).unwrap(), state);
+        }
+
+        #[test]
+        fn databases_convert_between_yaml_and_sqlite() {
+            let dir = test_dir("sqlite_convert");
+            let path = dir.join("db.yml");
+            let path_str = path.to_string_lossy();
+            DbFormat::Yaml.storage(&path_str).save(&state()).unwrap();
+            assert_eq!(DbFormat::detect(&path_str).unwrap(), DbFormat::Yaml);
+
+            DbFormat::Sqlite.storage(&path_str).save(&state()).unwrap();
+            assert_eq!(DbFormat::detect(&path_str).unwrap(), DbFormat::Sqlite);
+            assert_eq!(DbFormat::Sqlite.storage(&path_str).load().unwrap(), state());
+            assert!(!dir.join(".db.yml.tmp").exists());
+
+            DbFormat::Yaml.storage(&path_str).save(&state()).unwrap();
+            assert_eq!(DbFormat::detect(&path_str).unwrap(), DbFormat::Yaml);
+            assert_eq!(DbFormat::Yaml.storage(&path_str).load().unwrap(), state());
+        }
+
+        #[test]
+        fn files_that_are_not_databases_are_corrupt() {
+            let dir = test_dir("sqlite_not_a_database");
+            let path = dir.join("db.sqlite");
+            std::fs::write(&path, "SQLite format 3\0 but nothing like a database after that, not even close").unwrap();
+            assert_corrupt(load(&path), "");
+        }
+
+        #[test]
+        fn databases_without_a_version_are_corrupt() {
+            let dir = test_dir("sqlite_no_version");
+            assert_corrupt(load(&sqlite_db(&dir, "INSERT INTO lists VALUES (0, 'Todo');")), "Database has no version");
+
+            let dir = test_dir("sqlite_no_meta");
+            let path = dir.join("db.sqlite");
+            Connection::open(&path).unwrap().execute_batch("CREATE TABLE other (x);").unwrap();
+            assert_corrupt(load(&path), "no such table: meta");
+        }
+
+        #[test]
+        fn todos_in_missing_lists_are_corrupt() {
+            let dir = test_dir("sqlite_missing_list");
+            let path = sqlite_db(&dir, "
+                PRAGMA foreign_keys = OFF;
+                INSERT INTO meta VALUES ('version', '0.2');
+                INSERT INTO lists VALUES (0, 'Todo');
+                INSERT INTO todos VALUES (3, 0, 1, 'Orphan', 0);
+            ");
+            assert_corrupt(load(&path), "Todo in list 3, which doesn't exist");
+        }
+
+        #[test]
+        fn missing_and_newer_databases_are_unreadable() {
+            let dir = test_dir("sqlite_unreadable");
+            assert!(matches!(load(&dir.join("missing.sqlite")), Err(LoadError::Unreadable(_))));
+            let path = sqlite_db(&dir, "INSERT INTO meta VALUES ('version', '99.0');");
+            match load(&path) {
+                Err(LoadError::Unreadable(err)) => assert!(err.to_string().contains("newer than this tdi"), "{err}"),
+                other => panic!("expected an unreadable database, got {other:?}"),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+mod backend {
+    use super::SqliteFile;
+    use crate::{LoadError, State, Storage};
+    use anyhow::{anyhow, bail};
+
+    impl Storage for SqliteFile {
+        fn load(&self) -> Result<State, LoadError> {
+            Err(LoadError::Unreadable(anyhow!("{} is SQLite, but tdi was built without SQLite support", self.path)))
+        }
+
+        fn save(&self, _state: &State) -> anyhow::Result<()> {
+            bail!("Could not save {} as SQLite, since tdi was built without SQLite support", self.path)
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{SqliteFile, State, migrate_db};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Start of every SQLite database file.
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Where the database is kept, and in what form. The rest of the app only deals in [`State`]s.
+pub(crate) trait Storage {
+    /// Reads the database, migrating it from the version that saved it.
+    fn load(&self) -> Result<State, LoadError>;
+
+    /// Writes the database, replacing what was there. If this fails, the database is left as it was.
+    fn save(&self, state: &State) -> anyhow::Result<()>;
+}
+
+/// Reason a database could not be loaded.
+#[derive(Debug)]
+pub(crate) enum LoadError {
+    /// The database couldn't be read, or is from a newer version of tdi. It should be left alone.
+    Unreadable(anyhow::Error),
+    /// The database was read but doesn't make sense, like after a bad hand edit. Nothing in it can be used.
+    Corrupt(anyhow::Error),
+}
+
+/// File format of the database.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DbFormat {
+    Yaml,
+    Json,
+    Sqlite,
+}
+
+impl DbFormat {
+    /// Format going by a path's extension: json for .json, sqlite for .sqlite and .sqlite3, and yaml for anything else.
+    pub fn for_path(path: &str) -> Self {
+        let extension = Path::new(path).extension().map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("json") => Self::Json,
+            Some("sqlite" | "sqlite3") => Self::Sqlite,
+            _ => Self::Yaml,
+        }
+    }
+
+    /// Format of an existing database, going by its contents: SQLite databases by their header,
+    /// and JSON by starting like an object. Anything else is taken to be YAML.
+    pub fn detect(path: &str) -> std::io::Result<Self> {
+        let mut start = vec![];
+        std::fs::File::open(path)?.take(SQLITE_MAGIC.len() as u64).read_to_end(&mut start)?;
+        if start == SQLITE_MAGIC {
+            return Ok(Self::Sqlite);
+        }
+        let text = String::from_utf8_lossy(&start);
+        match text.trim_start().starts_with('{') {
+            true => Ok(Self::Json),
+            false => Ok(Self::Yaml),
+        }
+    }
+
+    /// Storage for a database at a path in this format.
+    pub fn storage(self, path: &str) -> Box<dyn Storage> {
+        let path = path.to_string();
+        match self {
+            Self::Yaml => Box::new(TextFile { path, format: TextFormat::Yaml }),
+            Self::Json => Box::new(TextFile { path, format: TextFormat::Json }),
+            Self::Sqlite => Box::new(SqliteFile { path }),
+        }
+    }
+}
+
+/// Database kept as a single YAML or JSON document.
+struct TextFile {
+    path: String,
+    format: TextFormat,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum TextFormat {
+    Yaml,
+    Json,
+}
+
+impl Storage for TextFile {
+    fn load(&self) -> Result<State, LoadError> {
+        let contents = std::fs::read_to_string(&self.path).map_err(|err| LoadError::Unreadable(err.into()))?;
+        self.decode(&contents)
+    }
+
+    fn save(&self, state: &State) -> anyhow::Result<()> {
+        write_atomic(Path::new(&self.path), self.encode(state)?.as_bytes())
+    }
+}
+
+impl TextFile {
+    /// Contents of a database file holding the state.
+    fn encode(&self, state: &State) -> anyhow::Result<String> {
+        match self.format {
+            TextFormat::Yaml => Ok(serde_yaml::to_string(state)?),
+            TextFormat::Json => Ok(serde_json::to_string_pretty(state)? + "\n"),
+        }
+    }
+
+    /// State held by the contents of a database file, migrated from the version that saved it.
+    fn decode(&self, contents: &str) -> Result<State, LoadError> {
+        let mut db = self.parse(contents).map_err(LoadError::Corrupt)?;
+        migrate_db(&mut db).map_err(LoadError::Unreadable)?;
+        serde_yaml::from_value(db).map_err(|err| LoadError::Corrupt(err.into()))
+    }
+
+    /// Parses the contents into a value that can be migrated, then read into a [`State`].
+    /// JSON that only parses as YAML, like a YAML flow mapping, is read as YAML.
+    fn parse(&self, contents: &str) -> anyhow::Result<serde_yaml::Value> {
+        if self.format == TextFormat::Json {
+            match serde_json::from_str::<serde_json::Value>(contents) {
+                Ok(db) => return Ok(serde_yaml::to_value(db)?),
+                Err(err) if serde_yaml::from_str::<serde_yaml::Value>(contents).is_err() => return Err(err.into()),
+                Err(_) => {}
+            }
+        }
+        Ok(serde_yaml::from_str(contents)?)
+    }
+}
+
+/// Replaces a file's contents without ever leaving it half written: the contents are written and synced to a
+/// temporary file in the same directory first, which is then renamed over the file. If anything fails, the file is
+/// left untouched.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    replace_atomic(path, |tmp_path| {
+        let mut file = std::fs::File::create(tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        Ok(())
+    })
+}
+
+/// Replaces a file with one written to a temporary path in the same directory by `write`, by renaming it over
+/// the file once written. If anything fails, the file is left untouched and the temporary file removed.
+pub(crate) fn replace_atomic(path: &Path, write: impl FnOnce(&Path) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let file_name = path.file_name().context("Path has no file name")?.to_string_lossy();
+    let tmp_path = path.with_file_name(format!(".{file_name}.tmp"));
+    let _ = std::fs::remove_file(&tmp_path); // Left over from a crash, if anything.
+    let written = write(&tmp_path).and_then(|()| Ok(std::fs::rename(&tmp_path, path)?));
+    if let Err(err) = written {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(err).with_context(|| format!("Could not write {}", path.display()));
+    }
+    // Syncs the rename itself, as best it can, where directories can be opened to do so
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rng, Todo, TodoList, test_dir};
+
+    /// Pieces todo names are made of: YAML indicators and keywords, whitespace and line breaks of every kind,
+    /// escapes, and text from outside ASCII.
+    const PIECES: &[&str] = &[
+        "-", "- ", ":", ": ", "?", ",", "[", "]", "{", "}", "#", " #", "&", "*", "!", "|", ">", "'", "\"", "%", "@", "`",
+        "~", "null", "Null", "NULL", "true", "false", "yes", "no", "on", "off", "y", "n", "<<", "---", "...", "=",
+        "0", "12", "-3", "1.5", "1e3", "0x1F", "0o17", ".inf", "-.inf", ".nan", "2024-01-31", "12:30",
+        " ", "  ", "\t", "\n", "\r", "\r\n", "\u{85}", "\u{2028}", "\u{2029}", "\u{feff}", "\u{0}", "\u{7}", "\u{1b}",
+        "\u{7f}", "\\", "\\n", "\\u0041", "foo", "bar baz", "café", "e\u{301}", "日本語", "👩‍💻", "🇫🇷", "\u{fffd}",
+    ];
+
+    impl Rng {
+        /// Arbitrary text, mixing tricky pieces with any character at all.
+        fn text(&mut self) -> String {
+            let mut text = String::new();
+            for _ in 0..self.below(8) {
+                match self.below(3) {
+                    0 => text.push(self.char()),
+                    _ => text.push_str(PIECES[self.below(PIECES.len())]),
+                }
+            }
+            text
+        }
+
+        fn state(&mut self) -> State {
+            let mut state = State::default();
+            state.todo_lists = (0..1 + self.below(3))
+                .map(|_| TodoList {
+                    name: self.text(),
+                    todos: (0..self.below(5))
+                        .map(|_| Todo {
+                            id: self.below(1000) as u64,
+                            name: self.text(),
+                            marked: self.below(2) == 1,
+                        })
+                        .collect(),
+                })
+                .collect();
+            for _ in 0..self.below(3) {
+                let mark = self.char();
+                state.marks.insert(mark, self.below(1000) as u64);
+            }
+            state
+        }
+    }
+
+    fn text_file(format: TextFormat) -> TextFile {
+        TextFile { path: String::new(), format }
+    }
+
+    fn round_trip(file: &TextFile, state: &State) -> State {
+        let contents = file.encode(state).unwrap();
+        match file.decode(&contents) {
+            Ok(loaded) => loaded,
+            Err(LoadError::Unreadable(err) | LoadError::Corrupt(err)) => panic!("{err:#} loading {contents:?}"),
+        }
+    }
+
+    #[test]
+    fn arbitrary_states_round_trip_through_yaml_and_json() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for format in [TextFormat::Yaml, TextFormat::Json] {
+            let file = text_file(format);
+            for _ in 0..2500 {
+                let state = rng.state();
+                assert_eq!(round_trip(&file, &state), state, "{format:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_piece_round_trips_alone_and_padded() {
+        for format in [TextFormat::Yaml, TextFormat::Json] {
+            let file = text_file(format);
+            for piece in PIECES {
+                for name in [piece.to_string(), format!(" {piece} "), format!("{piece}{piece}"), format!("a{piece}b")] {
+                    let mut state = State::default();
+                    state.todo_lists[0].todos.push(Todo::new(name.clone()));
+                    state.todo_lists[1].name = name;
+                    assert_eq!(round_trip(&file, &state), state, "{format:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file_without_leaving_the_temporary_one() {
+        let dir = test_dir("write_atomic");
+        let path = dir.join("db.yml");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::write(dir.join(".db.yml.tmp"), "left over from a crash").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.join(".db.yml.tmp").exists());
+    }
+
+    #[test]
+    fn failed_writes_leave_the_file_untouched() {
+        let dir = test_dir("write_atomic_failing");
+        let path = dir.join("db.yml");
+        std::fs::write(&path, "old").unwrap();
+
+        // Nothing can be written where a directory is in the way of the temporary file.
+        std::fs::create_dir(dir.join(".db.yml.tmp")).unwrap();
+        let err = write_atomic(&path, b"new").unwrap_err();
+        assert!(format!("{err:#}").starts_with(&format!("Could not write {}", path.display())), "{err:#}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        std::fs::remove_dir(dir.join(".db.yml.tmp")).unwrap();
+
+        // Nor is anything half written kept.
+        let result = replace_atomic(&path, |tmp_path| {
+            std::fs::write(tmp_path, "ne")?;
+            anyhow::bail!("disk full")
+        });
+        assert!(format!("{:#}", result.unwrap_err()).ends_with("disk full"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        assert!(!dir.join(".db.yml.tmp").exists());
+    }
+
+    #[test]
+    fn states_round_trip_through_files() {
+        let dir = test_dir("round_trip_files");
+        let mut rng = Rng(42);
+        for (file_name, format) in [("db.yml", DbFormat::Yaml), ("db.json", DbFormat::Json)] {
+            let path = dir.join(file_name).to_string_lossy().into_owned();
+            for _ in 0..20 {
+                let state = rng.state();
+                format.storage(&path).save(&state).unwrap();
+                assert_eq!(DbFormat::detect(&path).unwrap(), format);
+                assert_eq!(format.storage(&path).load().unwrap(), state);
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+/// Empty directory for a test to keep its files in, named after it, and emptied if left over from an earlier run.
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tdi-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Xorshift generator, so the cases are the same on every run without depending on a crate for randomness.
+pub(crate) struct Rng(pub u64);
+
+impl Rng {
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    pub fn char(&mut self) -> char {
+        loop {
+            if let Some(c) = char::from_u32(self.below(0x110000) as u32) {
+                return c;
+            }
+        }
+    }
+}
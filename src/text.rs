@@ -0,0 +1,238 @@
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Kind of character, used to find word boundaries.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            Self::Whitespace
+        }
+        else if c.is_alphanumeric() || c == '_' {
+            Self::Word
+        }
+        else {
+            Self::Punctuation
+        }
+    }
+}
+
+/// Grapheme index of the start of the word after the one at `grapheme_idx`, like vim's `w`.
+/// Runs of punctuation count as their own words.
+pub(crate) fn next_word_start(text: &str, grapheme_idx: usize) -> usize {
+    let classes = grapheme_classes(text);
+    let mut i = grapheme_idx.min(classes.len());
+    if let Some(&class) = classes.get(i) {
+        while i < classes.len() && classes[i] == class && class != CharClass::Whitespace {
+            i += 1;
+        }
+    }
+    while i < classes.len() && classes[i] == CharClass::Whitespace {
+        i += 1;
+    }
+    i
+}
+
+/// Grapheme index of the start of the word before `grapheme_idx`, like vim's `b`.
+/// Runs of punctuation count as their own words.
+pub(crate) fn prev_word_start(text: &str, grapheme_idx: usize) -> usize {
+    let classes = grapheme_classes(text);
+    let mut i = grapheme_idx.min(classes.len());
+    while i > 0 && classes[i - 1] == CharClass::Whitespace {
+        i -= 1;
+    }
+    if i == 0 {
+        return 0;
+    }
+    let class = classes[i - 1];
+    while i > 0 && classes[i - 1] == class {
+        i -= 1;
+    }
+    i
+}
+
+/// Class of each grapheme cluster in some text, going by its first character.
+fn grapheme_classes(text: &str) -> Vec<CharClass> {
+    text.graphemes(true)
+        .map(|grapheme| CharClass::of(grapheme.chars().next().unwrap_or(' ')))
+        .collect()
+}
+
+/// Number of grapheme clusters in some text, which is what the cursor moves over.
+pub(crate) fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Byte offset of the grapheme cluster at `grapheme_idx`, or the length of the text if it is past the end.
+pub(crate) fn byte_index(text: &str, grapheme_idx: usize) -> usize {
+    text.grapheme_indices(true)
+        .nth(grapheme_idx)
+        .map(|(byte_idx, _)| byte_idx)
+        .unwrap_or(text.len())
+}
+
+/// Number of terminal cells taken up by the text before the grapheme cluster at `grapheme_idx`.
+pub(crate) fn width_before(text: &str, grapheme_idx: usize) -> usize {
+    text[..byte_index(text, grapheme_idx)].width()
+}
+
+/// Grapheme index of the grapheme cluster drawn `col` cells into a range of grapheme clusters,
+/// or the end of the range if the text drawn is narrower.
+pub(crate) fn grapheme_at_col(text: &str, range: Range<usize>, col: usize) -> usize {
+    let mut used = 0;
+    for (i, grapheme) in text.graphemes(true).enumerate().take(range.end).skip(range.start) {
+        used += grapheme.width();
+        if used > col {
+            return i;
+        }
+    }
+    range.end
+}
+
+/// Text cut down to fit in `width` terminal cells, ending with an ellipsis if anything was cut.
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += grapheme.width();
+        if used + 1 > width {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+/// Text cut down to fit in `width` terminal cells by replacing its middle with an ellipsis,
+/// keeping both ends like the file name and the root of a path.
+pub(crate) fn elide_middle(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let graphemes: Vec<&str> = text.graphemes(true).collect();
+    let mut start = 0;
+    let mut end = graphemes.len();
+    let mut used = 1;
+    let mut from_end = true;
+    // Keeps graphemes from either end in turn, starting with the end, until the next one doesn't fit
+    while start < end {
+        let idx = match from_end {
+            true => end - 1,
+            false => start,
+        };
+        used += graphemes[idx].width();
+        if used > width {
+            break;
+        }
+        match from_end {
+            true => end -= 1,
+            false => start += 1,
+        }
+        from_end = !from_end;
+    }
+    format!("{}…{}", graphemes[..start].concat(), graphemes[end..].concat())
+}
+
+/// Grapheme index of the first grapheme cluster to show so the cursor at `grapheme_idx` fits in `width` cells,
+/// leaving a cell for the cursor itself. Text is only scrolled once the cursor would go past the end.
+pub(crate) fn scroll_to_cursor(text: &str, grapheme_idx: usize, width: usize) -> usize {
+    let widths: Vec<usize> = text.graphemes(true).map(|grapheme| grapheme.width()).collect();
+    let mut start = grapheme_idx.min(widths.len());
+    let mut used = 1;
+    while start > 0 && used + widths[start - 1] <= width {
+        start -= 1;
+        used += widths[start];
+    }
+    start
+}
+
+/// Splits text into rows of at most `width` cells, breaking after whitespace where possible.
+/// Returns the range of grapheme indices on each row. Empty text still takes a row.
+pub(crate) fn wrap_to_width(text: &str, width: usize) -> Vec<Range<usize>> {
+    let graphemes: Vec<&str> = text.graphemes(true).collect();
+    let mut rows = vec![];
+    let mut start = 0;
+    while start < graphemes.len() {
+        let mut end = start;
+        let mut used = 0;
+        let mut last_break = None;
+        while end < graphemes.len() {
+            let grapheme_width = graphemes[end].width();
+            if used + grapheme_width > width && end > start {
+                break;
+            }
+            used += grapheme_width;
+            end += 1;
+            if graphemes[end - 1].trim().is_empty() {
+                last_break = Some(end);
+            }
+        }
+        if let (true, Some(last_break)) = (end < graphemes.len(), last_break) {
+            end = last_break;
+        }
+        rows.push(start..end);
+        start = end;
+    }
+    if rows.is_empty() {
+        rows.push(0..0);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZWJ_EMOJI: &str = "👩\u{200d}💻";
+    const COMBINING: &str = "e\u{301}";
+    const FLAG: &str = "🇫🇷";
+
+    #[test]
+    fn multi_code_point_clusters_count_as_one_grapheme() {
+        for cluster in [ZWJ_EMOJI, COMBINING, FLAG] {
+            assert_eq!(grapheme_count(cluster), 1, "{cluster:?}");
+            let text = format!("a{cluster}b");
+            assert_eq!(grapheme_count(&text), 3, "{text:?}");
+        }
+        assert_eq!(grapheme_count(""), 0);
+        assert_eq!(grapheme_count("🇫🇷🇩🇪"), 2);
+    }
+
+    #[test]
+    fn byte_index_lands_on_cluster_boundaries() {
+        for cluster in [ZWJ_EMOJI, COMBINING, FLAG] {
+            let text = format!("a{cluster}b");
+            assert_eq!(byte_index(&text, 0), 0);
+            assert_eq!(byte_index(&text, 1), 1);
+            assert_eq!(byte_index(&text, 2), 1 + cluster.len());
+            assert_eq!(byte_index(&text, 3), text.len());
+            assert_eq!(byte_index(&text, 10), text.len());
+        }
+    }
+
+    #[test]
+    fn words_skip_whole_clusters() {
+        let text = format!("{ZWJ_EMOJI} pair with Ana");
+        assert_eq!(next_word_start(&text, 0), 2);
+        assert_eq!(prev_word_start(&text, 2), 0);
+        let text = format!("caf{COMBINING} ok");
+        assert_eq!(next_word_start(&text, 0), 5);
+        assert_eq!(prev_word_start(&text, 5), 0);
+    }
+}
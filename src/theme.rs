@@ -0,0 +1,229 @@
+use crate::color;
+use ratatui::style::{Color, Modifier};
+use serde_yaml::Value;
+use std::str::FromStr;
+
+/// Colors of each part of the interface.
+/// Defaults to the constants in [`color`], with roles overridable from the `theme` section of the config.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) struct Theme {
+    pub fg_marked: Color,
+    pub fg_warning: Color,
+    pub fg_error: Color,
+    pub bg_unselected: Color,
+    pub fg_unselected: Color,
+    pub bg_selected: Color,
+    pub fg_selected: Color,
+    pub border_unselected: Color,
+    pub border_selected: Color,
+    pub marked_modifier: Modifier,      // Style of marked todos on top of their color, like crossed out.
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            fg_marked: color::FG_MARKED.into(),
+            fg_warning: color::FG_WARNING.into(),
+            fg_error: color::FG_ERROR.into(),
+            bg_unselected: color::BG_UNSELECTED.into(),
+            fg_unselected: color::FG_UNSELECTED.into(),
+            bg_selected: color::BG_SELECTED.into(),
+            fg_selected: color::FG_SELECTED.into(),
+            border_unselected: color::BORDER_UNSELECTED.into(),
+            border_selected: color::BORDER_SELECTED.into(),
+            marked_modifier: Modifier::CROSSED_OUT | Modifier::DIM,
+        }
+    }
+}
+
+impl Theme {
+
+    /// Built-in theme by name: "dark", the default, "light" for terminals with a white background,
+    /// or "high-contrast".
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::default()),
+            "light" => Some(Self {
+                fg_marked: Color::Red,
+                fg_warning: Color::Rgb(0xb5, 0x89, 0x00),
+                fg_error: Color::Red,
+                bg_unselected: Color::White,
+                fg_unselected: Color::Black,
+                bg_selected: Color::Blue,
+                fg_selected: Color::White,
+                border_unselected: Color::DarkGray,
+                border_selected: Color::Blue,
+                marked_modifier: Modifier::CROSSED_OUT | Modifier::DIM,
+            }),
+            "high-contrast" => Some(Self {
+                fg_marked: Color::LightRed,
+                fg_warning: Color::LightYellow,
+                fg_error: Color::LightRed,
+                bg_unselected: Color::Black,
+                fg_unselected: Color::White,
+                bg_selected: Color::LightYellow,
+                fg_selected: Color::Black,
+                border_unselected: Color::White,
+                border_selected: Color::LightYellow,
+                marked_modifier: Modifier::CROSSED_OUT,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Theme from the `theme` section of the config. This is either the name of a preset,
+    /// or maps roles to color names or hex strings like "#ff8800", on top of the preset named by `preset` if any.
+    /// Anything that can't be used keeps its default, and is described in the warnings returned alongside.
+    pub fn from_config(section: &Value) -> (Self, Vec<String>) {
+        let mut theme = Self::default();
+        let mut warnings = vec![];
+        let preset = match section {
+            Value::String(preset) => Some(preset.as_str()),
+            Value::Mapping(mapping) => mapping.get("preset").and_then(Value::as_str),
+            _ => None,
+        };
+        if let Some(preset) = preset {
+            match Self::preset(preset) {
+                Some(preset) => theme = preset,
+                None => warnings.push(format!("unknown theme preset '{preset}'")),
+            }
+        }
+        let mapping = match section {
+            Value::Null | Value::String(_) => return (theme, warnings),
+            Value::Mapping(mapping) => mapping,
+            _ => {
+                warnings.push("theme should be a preset or map roles to colors".to_string());
+                return (theme, warnings);
+            }
+        };
+        for (role, color) in mapping {
+            let (Some(role), Some(color)) = (role.as_str(), color.as_str()) else {
+                warnings.push("theme entries should be a role and a color".to_string());
+                continue;
+            };
+            if role == "preset" {
+                continue;
+            }
+            if role == "marked_style" {
+                match parse_modifier(color) {
+                    Some(modifier) => theme.marked_modifier = modifier,
+                    None => warnings.push(format!("invalid style '{color}' for marked_style")),
+                }
+                continue;
+            }
+            let Some(role_color) = theme.role_mut(role) else {
+                warnings.push(format!("unknown theme role '{role}'"));
+                continue;
+            };
+            match Color::from_str(color) {
+                Ok(color) => *role_color = color,
+                Err(_) => warnings.push(format!("invalid color '{color}' for {role}")),
+            }
+        }
+        (theme, warnings)
+    }
+
+    /// Color of a role, by its name in the config.
+    fn role_mut(&mut self, role: &str) -> Option<&mut Color> {
+        match role {
+            "fg_marked" => Some(&mut self.fg_marked),
+            "fg_warning" => Some(&mut self.fg_warning),
+            "fg_error" => Some(&mut self.fg_error),
+            "bg_unselected" => Some(&mut self.bg_unselected),
+            "fg_unselected" => Some(&mut self.fg_unselected),
+            "bg_selected" => Some(&mut self.bg_selected),
+            "fg_selected" => Some(&mut self.fg_selected),
+            "border_unselected" => Some(&mut self.border_unselected),
+            "border_selected" => Some(&mut self.border_selected),
+            _ => None,
+        }
+    }
+}
+
+/// Text modifiers from a list of names separated by spaces, like "crossed_out dim", or "none".
+fn parse_modifier(names: &str) -> Option<Modifier> {
+    let mut modifier = Modifier::empty();
+    for name in names.split_whitespace() {
+        modifier |= match name {
+            "none" => Modifier::empty(),
+            "bold" => Modifier::BOLD,
+            "dim" => Modifier::DIM,
+            "italic" => Modifier::ITALIC,
+            "underlined" => Modifier::UNDERLINED,
+            "crossed_out" => Modifier::CROSSED_OUT,
+            _ => return None,
+        };
+    }
+    Some(modifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Theme from a `theme` section written in YAML.
+    fn theme(section: &str) -> (Theme, Vec<String>) {
+        Theme::from_config(&serde_yaml::from_str(section).unwrap())
+    }
+
+    #[test]
+    fn presets_are_named_by_a_string() {
+        assert_eq!(theme("~"), (Theme::default(), vec![]));
+        assert_eq!(theme("dark"), (Theme::default(), vec![]));
+        assert_eq!(theme("light"), (Theme::preset("light").unwrap(), vec![]));
+        assert_eq!(theme("high-contrast"), (Theme::preset("high-contrast").unwrap(), vec![]));
+        assert_eq!(theme("{preset: light}"), (Theme::preset("light").unwrap(), vec![]));
+    }
+
+    #[test]
+    fn roles_override_the_preset() {
+        let (overridden, warnings) = theme("{preset: light, fg_selected: yellow, border_selected: '#ff8800'}");
+        assert_eq!(warnings, Vec::<String>::new());
+        let expected = Theme {
+            fg_selected: Color::Yellow,
+            border_selected: Color::Rgb(0xff, 0x88, 0x00),
+            ..Theme::preset("light").unwrap()
+        };
+        assert_eq!(overridden, expected);
+        // Without a preset, over the default.
+        let (overridden, _) = theme("{bg_unselected: dark gray, fg_marked: '#0000FF'}");
+        let expected = Theme { bg_unselected: Color::DarkGray, fg_marked: Color::Rgb(0, 0, 0xff), ..Theme::default() };
+        assert_eq!(overridden, expected);
+    }
+
+    #[test]
+    fn anything_unusable_warns_and_keeps_the_default() {
+        type Case<'a> = (&'a str, &'a [&'a str]);
+        let cases: &[Case] = &[
+            ("solarized", &["unknown theme preset 'solarized'"]),
+            ("{preset: solarized}", &["unknown theme preset 'solarized'"]),
+            ("{fg_everything: red}", &["unknown theme role 'fg_everything'"]),
+            ("{fg_marked: reddish}", &["invalid color 'reddish' for fg_marked"]),
+            ("{fg_marked: '#12345'}", &["invalid color '#12345' for fg_marked"]),
+            ("{fg_marked: [red]}", &["theme entries should be a role and a color"]),
+            ("{marked_style: blinking}", &["invalid style 'blinking' for marked_style"]),
+            ("[dark]", &["theme should be a preset or map roles to colors"]),
+            ("{preset: dusk, fg_marked: reddish}", &["unknown theme preset 'dusk'", "invalid color 'reddish' for fg_marked"]),
+        ];
+        for &(section, expected) in cases {
+            assert_eq!(theme(section), (Theme::default(), expected.iter().map(|s| s.to_string()).collect()), "{section}");
+        }
+        // The rest of the section still applies.
+        let (partly, warnings) = theme("{fg_marked: reddish, fg_error: blue}");
+        assert_eq!(partly, Theme { fg_error: Color::Blue, ..Theme::default() });
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn marked_style_lists_modifiers() {
+        assert_eq!(parse_modifier("none"), Some(Modifier::empty()));
+        assert_eq!(parse_modifier(""), Some(Modifier::empty()));
+        assert_eq!(parse_modifier("crossed_out dim"), Some(Modifier::CROSSED_OUT | Modifier::DIM));
+        assert_eq!(parse_modifier("  bold   italic underlined "), Some(Modifier::BOLD | Modifier::ITALIC | Modifier::UNDERLINED));
+        assert_eq!(parse_modifier("bold blinking"), None);
+        assert_eq!(parse_modifier("Bold"), None);
+        let (plain, warnings) = theme("{preset: high-contrast, marked_style: none}");
+        assert_eq!(plain, Theme { marked_modifier: Modifier::empty(), ..Theme::preset("high-contrast").unwrap() });
+        assert!(warnings.is_empty());
+    }
+}
@@ -1,10 +1,12 @@
-use crate::{Mode, color};
+use crate::{Mode, Selection, Theme, byte_index, grapheme_at_col, grapheme_count, scroll_to_cursor, truncate_to_width, width_before, wrap_to_width};
 use ratatui::Frame;
 use ratatui::layout::{Alignment, Rect};
-use ratatui::style::Stylize;
+use ratatui::style::{Modifier, Style, Stylize};
 use ratatui::text::Line;
-use ratatui::widgets::{Block, Borders};
+use ratatui::widgets::{Block, Borders, Scrollbar, ScrollbarOrientation, ScrollbarState};
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub(crate) struct TodoList {
@@ -14,59 +16,354 @@ pub(crate) struct TodoList {
 
 impl TodoList {
 
-    pub fn render(&self,
-        is_selected: bool,
-        todo_selected: usize,
-        char_selected: usize,
-        mode: Mode,
-        area: Rect,
-        frame: &mut Frame,
-    ) { 
+    pub fn render(&self, view: &ListView, area: Rect, frame: &mut Frame) {
         // Todo container
-        let color = if is_selected { color::BORDER_SELECTED } else { color::BORDER_UNSELECTED };
+        let color = if view.is_selected { view.theme.border_selected } else { view.theme.border_unselected };
         let block = Block::default()
             .title(self.name.as_ref())
             .borders(Borders::all())
             .title_alignment(Alignment::Center)
             .fg(color);
         frame.render_widget(block, area);
+        if self.todos.is_empty() {
+            return;
+        }
 
-        // Todos
-        let mut line_area = area;
-        line_area.x += 2;
-        if !self.todos.is_empty() {
-            line_area.width -= 4;
-            line_area.height = 1;
-            let todo_selected = todo_selected.min(self.todos.len()-1);
-            for (i, todo) in self.todos.iter().enumerate() {
-                let is_todo_selected = mode == Mode::Normal && is_selected && i == todo_selected;
-                let bg_color = match is_todo_selected {
-                    false => color::BG_UNSELECTED,
-                    true => color::BG_SELECTED,
-                };
-                let fg_color = match (is_todo_selected, todo.marked) {
-                    (_, true) => color::FG_MARKED,
-                    (false, false) => color::FG_UNSELECTED,
-                    (true, false) => color::FG_SELECTED,
-                };
-                line_area.y += 1;
-                if todo.name.is_empty() {
-                    let todo_line = Line::from("•").bg(bg_color).fg(fg_color);
-                    frame.render_widget(todo_line, line_area);
+        // Todos, starting from the first row scrolled into view, after the line number gutter if any
+        let gutter_width = self.gutter_width(view);
+        let line_area = Rect {
+            x: area.x + 2 + gutter_width,
+            y: area.y + 1,
+            width: area.width.saturating_sub(4 + gutter_width),
+            height: 1,
+        };
+        let visible = area.height.saturating_sub(2) as usize;
+        if line_area.width == 0 || visible == 0 {
+            return;
+        }
+        let name_width = self.name_width(view, area);
+        let todo_selected = view.selection.todo.min(self.todos.len()-1);
+        let todo_anchor = view.selection.anchor.min(self.todos.len()-1);
+        let visual_range = todo_selected.min(todo_anchor)..=todo_selected.max(todo_anchor);
+        let mut row = 0;
+        for (i, todo) in self.todos.iter().enumerate() {
+            if row >= view.scroll + visible {
+                break;
+            }
+            let is_todo_selected = view.is_selected && match view.mode {
+                Mode::Normal => i == todo_selected,
+                Mode::Visual => visual_range.contains(&i),
+                _ => false,
+            };
+            let is_editing = view.is_selected && view.mode == Mode::Insert && i == todo_selected;
+            let bg_color = match is_todo_selected {
+                false => view.theme.bg_unselected,
+                true => view.theme.bg_selected,
+            };
+            let fg_color = match (is_todo_selected, todo.marked) {
+                (_, true) => view.theme.fg_marked,
+                (false, false) => view.theme.fg_unselected,
+                (true, false) => view.theme.fg_selected,
+            };
+            // Dimming a selected todo would make it hard to read on the selection's background.
+            let modifier = match (todo.marked, is_todo_selected) {
+                (false, _) => Modifier::empty(),
+                (true, false) => view.theme.marked_modifier,
+                (true, true) => view.theme.marked_modifier - Modifier::DIM,
+            };
+            let todo_rows = todo_rows(todo, view, is_editing, name_width);
+            for (todo_row, text) in todo_rows.iter().enumerate() {
+                if !(view.scroll..view.scroll + visible).contains(&row) {
+                    row += 1;
+                    continue;
                 }
-                else {
-                    let todo_name = format!("• {}", todo.name);
-                    let todo_line = Line::from(todo_name).bg(bg_color).fg(fg_color);
-                    frame.render_widget(todo_line, line_area);
+                let row_area = Rect { y: line_area.y + (row - view.scroll) as u16, ..line_area };
+                if todo_row == 0 {
+                    if let Some((mark, _)) = view.marks.iter().find(|(_, id)| **id == todo.id) {
+                        let mark_area = Rect { x: area.x + 1, width: 1, ..row_area };
+                        frame.render_widget(Line::from(mark.to_string()).fg(view.theme.border_unselected), mark_area);
+                    }
+                    if gutter_width > 0 {
+                        let number = match view.line_numbers {
+                            LineNumbers::Relative if i != todo_selected => i.abs_diff(todo_selected),
+                            _ => i + 1,
+                        };
+                        let number_area = Rect { x: area.x + 2, width: gutter_width, ..row_area };
+                        let number_text = format!("{number:>width$}", width = gutter_width as usize - 1);
+                        frame.render_widget(Line::from(number_text).fg(view.theme.border_unselected), number_area);
+                    }
                 }
+                let prefix = view.todo_prefix.get(todo.marked);
+                let prefix = match (todo_row, todo.name.is_empty()) {
+                    (0, true) => prefix.trim_end().to_string(),
+                    (0, false) => prefix.to_string(),
+                    _ => " ".repeat(view.todo_prefix.width() as usize),
+                };
+                let todo_line = Line::from(format!("{prefix}{text}")).bg(bg_color).fg(fg_color).add_modifier(modifier);
+                frame.render_widget(todo_line, row_area);
+
+                // Sets cursor position
+                if is_editing && cursor_row(todo, view, name_width) == todo_row {
+                    let cursor_x = row_area.x + view.todo_prefix.width() + cursor_col(todo, view, name_width) as u16;
+                    let last_x = area.right().saturating_sub(2); // Last column inside the border.
+                    frame.set_cursor_position((cursor_x.min(last_x), row_area.y));
+                }
+                row += 1;
+            }
+        }
+
+        // Scrollbar over the right border, only when some rows are out of view
+        let total_rows: usize = self.row_counts(view, area).iter().sum();
+        if total_rows > visible {
+            let scrollbar_area = Rect {
+                x: area.x,
+                y: area.y + 1,
+                width: area.width,
+                height: visible as u16,
+            };
+            let mut scrollbar_state = ScrollbarState::new(total_rows - visible + 1)
+                .viewport_content_length(visible)
+                .position(view.scroll);
+            let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .track_symbol(Some("│"))
+                .track_style(Style::new().fg(color))
+                .thumb_style(Style::new().fg(view.theme.fg_unselected));
+            frame.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
+        }
+    }
+
+    /// Index of the todo drawn on the row at `y` when drawn in some area, if any.
+    /// None for rows on the borders or below the last todo.
+    pub fn todo_at(&self, view: &ListView, area: Rect, y: u16) -> Option<usize> {
+        let visible = area.height.saturating_sub(2);
+        if y <= area.y || y > area.y + visible {
+            return None;
+        }
+        let mut row = (y - area.y - 1) as usize + view.scroll;
+        for (i, row_count) in self.row_counts(view, area).into_iter().enumerate() {
+            if row < row_count {
+                return Some(i);
+            }
+            row -= row_count;
+        }
+        None
+    }
+
+    /// Grapheme index in the name of the todo at `todo_idx` drawn at `x` and `y` when drawn in some area,
+    /// as if it weren't being edited. Clicks past the end of a row give the end of the row.
+    pub fn char_at(&self, view: &ListView, area: Rect, todo_idx: usize, x: u16, y: u16) -> usize {
+        let Some(todo) = self.todos.get(todo_idx) else { return 0 };
+        let name_width = self.name_width(view, area);
+        let name_x = area.x + 2 + self.gutter_width(view) + view.todo_prefix.width();
+        let col = x.saturating_sub(name_x) as usize;
+        let range = match view.wrap {
+            false => 0..grapheme_count(&todo.name),
+            true => {
+                let rows = wrap_to_width(&todo.name, name_width);
+                let first_row: usize = self.row_counts(view, area)[..todo_idx].iter().sum();
+                let row = (y.saturating_sub(area.y + 1) as usize + view.scroll).saturating_sub(first_row);
+                rows[row.min(rows.len() - 1)].clone()
             }
+        };
+        grapheme_at_col(&todo.name, range, col)
+    }
+
+    /// Number of rows each todo takes up when drawn in some area.
+    fn row_counts(&self, view: &ListView, area: Rect) -> Vec<usize> {
+        let name_width = self.name_width(view, area);
+        match view.wrap {
+            false => vec![1; self.todos.len()],
+            true => self.todos.iter().map(|todo| wrap_to_width(&todo.name, name_width).len()).collect(),
         }
+    }
 
-        // Sets cursor position
-        if mode == Mode::Insert && is_selected {
-            let cursor_x = 4 + area.x + char_selected as u16;
-            let cursor_y = 1 + area.y + todo_selected as u16;
-            frame.set_cursor_position((cursor_x, cursor_y));
+    /// Number of cells taken up by line numbers and the space after them, 0 when they are hidden.
+    fn gutter_width(&self, view: &ListView) -> u16 {
+        match view.line_numbers {
+            LineNumbers::Off => 0,
+            LineNumbers::Relative if !view.is_selected => 0,
+            LineNumbers::Absolute | LineNumbers::Relative => self.todos.len().to_string().len() as u16 + 1,
+        }
+    }
+
+    /// Number of cells available for todo names when drawn in some area, after borders, padding, line numbers and bullets.
+    fn name_width(&self, view: &ListView, area: Rect) -> usize {
+        area.width.saturating_sub(4 + self.gutter_width(view) + view.todo_prefix.width()) as usize
+    }
+
+    /// Scroll offset, in rows, that keeps the selected todo in view with the fewest rows scrolled from the view's.
+    /// Also scrolls back up when todos were removed from the end, so the list never shows blank rows it could fill.
+    pub fn scroll_into_view(&self, view: &ListView, area: Rect) -> usize {
+        let visible = area.height.saturating_sub(2) as usize;
+        let row_counts = self.row_counts(view, area);
+        let total_rows: usize = row_counts.iter().sum();
+        let mut scroll = view.scroll.min(total_rows.saturating_sub(visible));
+        if view.is_selected {
+            let selected = view.selection.todo.min(self.todos.len().saturating_sub(1));
+            let first_row: usize = row_counts[..selected].iter().sum();
+            let end_row = first_row + row_counts.get(selected).copied().unwrap_or(0);
+            if first_row < scroll {
+                scroll = first_row;
+            }
+            else if end_row > scroll + visible {
+                scroll = end_row.saturating_sub(visible).min(first_row);
+            }
+        }
+        scroll
+    }
+}
+
+/// Text of each row a todo's name is drawn on.
+/// Unwrapped names take a single row, cut short unless being edited, in which case they scroll with the cursor.
+fn todo_rows(todo: &Todo, view: &ListView, is_editing: bool, name_width: usize) -> Vec<String> {
+    if view.wrap {
+        return wrap_to_width(&todo.name, name_width)
+            .into_iter()
+            .map(|range| todo.name[byte_index(&todo.name, range.start)..byte_index(&todo.name, range.end)].to_string())
+            .collect();
+    }
+    let shown_name = match is_editing {
+        false => &todo.name[..],
+        true => {
+            let start = scroll_to_cursor(&todo.name, view.selection.char, name_width);
+            &todo.name[byte_index(&todo.name, start)..]
+        }
+    };
+    vec![truncate_to_width(shown_name, name_width)]
+}
+
+/// Row of a todo the cursor is on, always the first unless the todo is wrapped.
+fn cursor_row(todo: &Todo, view: &ListView, name_width: usize) -> usize {
+    if !view.wrap {
+        return 0;
+    }
+    let rows = wrap_to_width(&todo.name, name_width);
+    let char = view.selection.char.min(grapheme_count(&todo.name));
+    rows.iter().position(|range| char < range.end).unwrap_or(rows.len() - 1)
+}
+
+/// Column of the cursor, in cells from the start of the todo's name on the row it is on.
+fn cursor_col(todo: &Todo, view: &ListView, name_width: usize) -> usize {
+    let char = view.selection.char;
+    let start = match view.wrap {
+        false => scroll_to_cursor(&todo.name, char, name_width),
+        true => {
+            let rows = wrap_to_width(&todo.name, name_width);
+            rows[cursor_row(todo, view, name_width)].start
+        }
+    };
+    width_before(&todo.name, char) - width_before(&todo.name, start)
+}
+
+/// How a [`TodoList`] is drawn, as decided by the [`App`](crate::App).
+pub(crate) struct ListView<'a> {
+    pub is_selected: bool,              // True if the list has focus.
+    pub selection: Selection,           // Selection of the app, only highlighted if the list has focus.
+    pub marks: &'a BTreeMap<char, u64>, // Ids of todos bookmarked by letter.
+    pub mode: Mode,                     // Mode of the app.
+    pub scroll: usize,                  // Index of the first row shown.
+    pub wrap: bool,                     // If true, long names wrap onto more rows instead of being cut short.
+    pub line_numbers: LineNumbers,      // How todos are numbered in the gutter.
+    pub todo_prefix: &'a TodoPrefix,    // What todos are prefixed with.
+    pub theme: &'a Theme,               // Colors to draw with.
+}
+
+/// What each todo in a [`TodoList`] is prefixed with.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TodoStyle {
+    /// A bullet, like "• write tests".
+    #[default]
+    Bullet,
+    /// A Markdown task list checkbox, checked for marked todos, like "[x] fix CI".
+    Checkbox,
+}
+
+impl TodoStyle {
+    /// Prefix of a todo, including the space separating it from the name.
+    pub fn prefix(self, marked: bool) -> &'static str {
+        match (self, marked) {
+            (Self::Bullet, _) => "• ",
+            (Self::Checkbox, false) => "[ ] ",
+            (Self::Checkbox, true) => "[x] ",
+        }
+    }
+}
+
+/// Prefixes drawn before todo names: those of a [`TodoStyle`], with glyphs overridable from the config.
+/// Both prefixes are padded to the same width, so names line up whether marked or not.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) struct TodoPrefix {
+    unmarked: String,
+    marked: String,
+    width: u16,
+}
+
+impl TodoPrefix {
+
+    /// Prefixes of a style, with the glyph of unmarked todos replaced by `bullet` and that of marked todos
+    /// by `marked_bullet`, falling back to `bullet`. An empty glyph leaves names unprefixed.
+    pub fn new(style: TodoStyle, bullet: Option<&str>, marked_bullet: Option<&str>) -> Self {
+        let from_glyph = |glyph: &str| match glyph.is_empty() {
+            true => String::new(),
+            false => format!("{glyph} "),
+        };
+        let unmarked = match bullet {
+            Some(glyph) => from_glyph(glyph),
+            None => style.prefix(false).to_string(),
+        };
+        let marked = match marked_bullet.or(bullet) {
+            Some(glyph) => from_glyph(glyph),
+            None => style.prefix(true).to_string(),
+        };
+        let width = unmarked.width().max(marked.width());
+        let pad = |prefix: String| {
+            let padding = " ".repeat(width - prefix.width()); // Not format's padding, which counts chars.
+            prefix + &padding
+        };
+        Self {
+            unmarked: pad(unmarked),
+            marked: pad(marked),
+            width: width as u16,
+        }
+    }
+
+    /// Prefix of a todo, including the space separating it from the name.
+    pub fn get(&self, marked: bool) -> &str {
+        match marked {
+            false => &self.unmarked,
+            true => &self.marked,
+        }
+    }
+
+    /// Number of cells taken up by either prefix.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+}
+
+/// How todos are numbered in the left gutter of a [`TodoList`].
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LineNumbers {
+    /// No line numbers.
+    #[default]
+    Off,
+    /// Todos are numbered from 1 in every list.
+    Absolute,
+    /// Todos are numbered by distance from the selected one, in the selected list only.
+    Relative,
+}
+
+impl LineNumbers {
+    /// Numbering after this one, cycling back to none.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Off,
         }
     }
 }
@@ -74,6 +371,9 @@ impl TodoList {
 /// A single todo in a [`TodoList`]
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Default, Debug)]
 pub(crate) struct Todo {
+    /// Identifies the todo even as it moves between lists. 0 until assigned by the [`App`](crate::App).
+    #[serde(default)]
+    pub id: u64,
     pub name: String,
     #[serde(default)]
     pub marked: bool,
@@ -82,6 +382,7 @@ pub(crate) struct Todo {
 impl Todo {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
+            id: 0,
             name: name.into(),
             marked: false,
         }